@@ -1,6 +1,10 @@
 pub mod aco_parameters;
 pub mod aco_solver;
 mod ant;
+pub mod class_ordering;
 pub mod colony;
+pub mod edge_cost;
 pub mod graph;
+pub mod run_event;
+pub mod stopping_criteria;
 pub mod violations;
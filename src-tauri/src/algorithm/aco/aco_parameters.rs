@@ -1,8 +1,11 @@
-use std::sync::Mutex;
 use tauri::Manager;
 use log::info;
+use super::class_ordering::ClassOrdering;
 
 use serde::{Deserialize, Serialize};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AcoParameters {
     pub num_of_ants: usize,
@@ -22,29 +25,42 @@ pub struct AcoParameters {
     pub tau_max: f64,
     pub ant_prob_random: f64,
     pub super_not_change: usize,
+    #[serde(default)]
+    pub class_ordering: ClassOrdering,
+    //0.0のときは分岐数による停滞検知をしない
+    #[serde(default)]
+    pub min_branching_factor: f64,
+    #[serde(default = "default_restart_ratio")]
+    pub restart_ratio: f64,
 }
 
-pub struct AcoParametersManager {
-    pub parameters: Mutex<Option<AcoParameters>>,
+fn default_restart_ratio() -> f64 {
+    1.0
 }
 
 static days_of_week: [&str; 7] = ["月", "火", "水", "木", "金", "土", "日"];
+
+pub fn get_day_label(day: usize) -> String {
+    format!("{}曜日", days_of_week[day % days_of_week.len()])
+}
+
+pub fn get_period_labels(parameters: &AcoParameters) -> Vec<String> {
+    let mut res = Vec::new();
+    for i in 0..parameters.num_of_periods {
+        res.push(format!(
+            "{} {}限",
+            get_day_label(i / parameters.num_of_day_lengths),
+            i % parameters.num_of_day_lengths + 1
+        ));
+    }
+    res
+}
+
 #[tauri::command]
 pub fn handle_get_periods(
-    parameters_manager: tauri::State<'_, AcoParametersManager>,
-) -> Result<Vec<String>, String> {
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<Vec<String>, CommandError> {
     info!("called handle_get_periods");
-    let parameters = parameters_manager.parameters.lock().unwrap();
-    let mut res = Vec::new();
-    if let Some(parameters) = &*parameters {
-        for i in 0..parameters.num_of_periods {
-            res.push(format!(
-                "{}曜日 {}限",
-                days_of_week[(i / parameters.num_of_day_lengths) % days_of_week.len()],
-                i % parameters.num_of_day_lengths + 1
-            ));
-        }
-        return Ok(res);
-    }
-    Err("No parameters".to_string())
+    let session = session_manager.session.lock_state()?;
+    Ok(get_period_labels(session.get_parameters()?))
 }
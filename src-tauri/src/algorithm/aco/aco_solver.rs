@@ -1,17 +1,23 @@
 use super::super::time_table::cell::Cell;
 use super::aco_parameters::AcoParameters;
 use super::ant::Ant;
+use super::class_ordering::ClassOrdering;
 use super::colony::Colony;
 use super::graph::Graph;
+use super::run_event::{RunEvent, StagnationReason};
+use super::stopping_criteria::{RunSummary, StoppingCriteria};
+use crate::algorithm::time_table::resolve::PreviousAssignment;
 use super::violations::Violations;
 use crate::{
-    algorithm::time_table::cell::ActiveCell,
+    algorithm::time_table::{cell::ActiveCell, TimeTable},
     input::{class, Input},
 };
-use std::sync::Mutex;
 use tauri::Manager;
 use serde::{Deserialize, Serialize};
 use log::info;
+use std::time::Instant;
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
 
 #[derive(Serialize,Deserialize,Clone)]
 pub struct ACOSolver {
@@ -22,8 +28,19 @@ pub struct ACOSolver {
     pub cnt_super_not_change: usize,
     pub input: Input,
     pub cnt: usize,
+    #[serde(default)]
+    pub run_events: Vec<RunEvent>,
+    //前回のリセットから何世代たったか
+    #[serde(default)]
+    pub cnt_since_restart: usize,
 }
 
+static BRANCHING_FACTOR_LAMBDA: f64 = 0.05;
+//部分リセットでは分岐数が回復しないので、リセット直後はしばらく分岐数を見ない
+static RESTART_COOLDOWN_GENERATIONS: usize = 20;
+//プロジェクトに保存されるので古いものから捨てる
+static MAX_RUN_EVENTS: usize = 1000;
+
 impl ACOSolver {
     pub fn run_aco(&mut self, graph: &super::graph::Graph) {
         for _ in 0..self.parameters.max_iterations {
@@ -68,20 +85,75 @@ impl ACOSolver {
         res
     }
     pub fn run_aco_while_none_violation(&mut self) {
+        self.cnt += 1;
         self.update_aco();
         while self.get_best_ant_total_violations().len() > 0 {
+            self.cnt += 1;
             self.update_aco();
-            self.cnt_super_not_change += 1;
         }
     }
 
     pub fn run_aco_times(&mut self, times: usize) {
-        self.cnt += times;
         for _ in 0..times {
+            self.cnt += 1;
             self.update_aco();
         }
     }
 
+    pub fn run_aco_until(&mut self, criteria: &StoppingCriteria) -> Result<RunSummary, String> {
+        if !criteria.is_bounded() {
+            return Err("stopping criteria needs a time, generation or no improvement limit".to_string());
+        }
+        let start = Instant::now();
+        let mut generations = 0;
+        let mut best_score = f64::MAX;
+        let mut generations_without_improvement = 0;
+        loop {
+            self.cnt += 1;
+            self.update_aco();
+            generations += 1;
+            let score = self.get_best_ant_score();
+            let hard_violations = self.get_best_ant_count_violations();
+            if score < best_score {
+                best_score = score;
+                generations_without_improvement = 0;
+            } else {
+                generations_without_improvement += 1;
+            }
+            if let Some(reason) = criteria.check(
+                start.elapsed(),
+                generations,
+                score,
+                hard_violations,
+                generations_without_improvement,
+            ) {
+                //warm startなどで以前の解の方が良い場合はそれを結果にする
+                if self.super_ant.is_some() && self.get_super_ant_score() < score {
+                    self.best_ant = self.super_ant.clone();
+                }
+                let score = self.get_best_ant_score();
+                let hard_violations = self.get_best_ant_count_violations();
+                let elapsed_secs = start.elapsed().as_secs_f64();
+                info!(
+                    "stopped at generation {}: {:?}, {}s",
+                    self.cnt, reason, elapsed_secs
+                );
+                self.push_run_event(RunEvent::Stop {
+                    generation: self.cnt,
+                    reason,
+                    elapsed_secs,
+                });
+                return Ok(RunSummary {
+                    reason,
+                    generations,
+                    elapsed_secs,
+                    score,
+                    hard_violations,
+                });
+            }
+        }
+    }
+
     pub fn get_super_ant_score(&self) -> f64 {
         if let Some(ant) = &self.super_ant {
             return ant.calc_all_path_length(self.colony.get_graph());
@@ -94,6 +166,12 @@ impl ACOSolver {
         }
         return 0.0;
     }
+    pub fn get_best_ant_count_violations(&self) -> usize {
+        if let Some(ant) = &self.best_ant {
+            return ant.count_violations(self.colony.get_graph());
+        }
+        return 0;
+    }
 
     fn update_aco(&mut self) {
         self.update_colony();
@@ -104,11 +182,13 @@ impl ACOSolver {
                     < super_ant.calc_all_path_length(self.colony.get_graph())
                 {
                     self.super_ant = Some(best_ant.clone());
+                    self.cnt_super_not_change = 0;
                 } else {
-                    self.cnt_super_not_change;
+                    self.cnt_super_not_change += 1;
                 }
             } else {
                 self.super_ant = Some(best_ant.clone());
+                self.cnt_super_not_change = 0;
             }
             /*
             println!(
@@ -117,13 +197,60 @@ impl ACOSolver {
             );
             */
         }
-        if self.cnt_super_not_change > self.parameters.super_not_change {
-            println!("reset pheromone!");
-            self.colony.reset_pheromone();
-            self.cnt_super_not_change = 0;
+        self.cnt_since_restart += 1;
+        if let Some(reason) = self.detect_stagnation() {
+            self.restart(reason);
         }
     }
 
+    fn detect_stagnation(&self) -> Option<StagnationReason> {
+        if self.cnt_super_not_change >= self.parameters.super_not_change {
+            return Some(StagnationReason::NoImprovement {
+                generations: self.cnt_super_not_change,
+            });
+        }
+        if self.parameters.min_branching_factor > 0.0
+            && self.cnt_since_restart >= RESTART_COOLDOWN_GENERATIONS
+        {
+            let branching_factor = self
+                .colony
+                .get_graph()
+                .calc_branching_factor(BRANCHING_FACTOR_LAMBDA);
+            if branching_factor < self.parameters.min_branching_factor {
+                return Some(StagnationReason::LowBranchingFactor { branching_factor });
+            }
+        }
+        None
+    }
+
+    fn restart(&mut self, reason: StagnationReason) {
+        let ratio = self.parameters.restart_ratio;
+        info!(
+            "reset pheromone at generation {}: {:?}, ratio {}",
+            self.cnt, reason, ratio
+        );
+        self.colony.reset_pheromone(ratio);
+        self.cnt_super_not_change = 0;
+        self.cnt_since_restart = 0;
+        self.push_run_event(RunEvent::Restart {
+            generation: self.cnt,
+            reason,
+            ratio,
+        });
+    }
+
+    fn push_run_event(&mut self, event: RunEvent) {
+        if self.run_events.len() >= MAX_RUN_EVENTS {
+            let overflow = self.run_events.len() + 1 - MAX_RUN_EVENTS;
+            self.run_events.drain(..overflow);
+        }
+        self.run_events.push(event);
+    }
+
+    pub fn get_run_events(&self) -> &Vec<RunEvent> {
+        &self.run_events
+    }
+
     fn update_colony(&mut self) {
         self.colony.update_colony();
         self.update_next_pheromone();
@@ -140,9 +267,17 @@ impl ACOSolver {
     pub fn get_parameters(&self) -> AcoParameters {
         self.parameters.clone()
     }
+    pub fn set_parameters(&mut self, parameters: AcoParameters) {
+        self.colony.set_parameters(parameters.clone());
+        self.parameters = parameters;
+    }
     pub fn get_best_ant(&self) -> Option<Ant> {
         return self.best_ant.clone();
     }
+    //Noneの授業を置いていないant、手動での移動先の評価に使う
+    pub fn create_ant_from_assignments(&self, assignments: &Vec<Option<[usize; 2]>>) -> Ant {
+        return Ant::from_assignments(self.parameters.clone(), assignments, self.colony.get_graph());
+    }
     pub fn get_best_ant_same_group_violations(&self) -> Vec<Violations> {
         if let Some(best_ant) = &self.best_ant {
             return best_ant.get_same_students_group_violations();
@@ -202,6 +337,86 @@ impl ACOSolver {
         return (self.parameters.num_of_ants as f64) * (self.parameters.q / self.parameters.rou);
     }
 
+    //時間割からフェロモンとsuper_antを初期化し、なるべく変更の少ない再最適化をする
+    pub fn warm_start(&mut self, timetable: &TimeTable, strength: f64) -> Result<(), String> {
+        //途中で失敗してsolverが中途半端に変わらないよう、先に全授業があるか確かめる
+        let cells: Vec<&ActiveCell> = timetable.class_list.iter().flatten().collect();
+        if cells.len() != self.parameters.num_of_classes {
+            return Err("timetable does not contain all classes".to_string());
+        }
+        if cells.iter().any(|cell| {
+            cell.class_index >= self.parameters.num_of_classes
+                || cell.room >= self.parameters.num_of_rooms
+                || cell.period >= self.parameters.num_of_periods
+        }) {
+            return Err("timetable does not match the solver".to_string());
+        }
+        let max_pheromone = self.ceiling_max_pheromone();
+        let tau_min = self.parameters.tau_min;
+        let mut corresponding_crp = vec![[0, 0]; self.parameters.num_of_classes];
+        for cell in cells.iter() {
+            corresponding_crp[cell.class_index] = [cell.room, cell.period];
+            self.colony.get_graph_as_mut().seed_pheromone(
+                cell.class_index,
+                cell.room,
+                cell.period,
+                strength,
+                tau_min,
+                max_pheromone,
+            );
+        }
+        self.colony.get_graph_as_mut().load_cells(&timetable.class_list);
+        let ant = Ant::from_corresponding_crp(
+            self.parameters.clone(),
+            &corresponding_crp,
+            self.colony.get_graph(),
+        );
+        self.best_ant = Some(ant.clone());
+        self.super_ant = Some(ant);
+        self.cnt_super_not_change = 0;
+        Ok(())
+    }
+
+    //入力変更前の配置を移動コストとして与え、その配置のフェロモンを強めておく
+    pub fn set_previous_assignments(
+        &mut self,
+        previous_assignments: Vec<Option<PreviousAssignment>>,
+        strength: f64,
+    ) {
+        let max_pheromone = self.ceiling_max_pheromone();
+        let tau_min = self.parameters.tau_min;
+        for (class_index, previous) in previous_assignments.iter().enumerate() {
+            if let Some(PreviousAssignment {
+                room: Some(room),
+                period,
+            }) = previous
+            {
+                if *period + self.input.get_classes()[class_index].serial_size
+                    <= self.parameters.num_of_periods
+                {
+                    self.colony.get_graph_as_mut().seed_pheromone(
+                        class_index,
+                        *room,
+                        *period,
+                        strength,
+                        tau_min,
+                        max_pheromone,
+                    );
+                }
+            }
+        }
+        self.colony
+            .get_graph_as_mut()
+            .set_previous_assignments(previous_assignments);
+    }
+
+    //再計画が終わったら、以降の実行では移動コストを付けない
+    pub fn clear_previous_assignments(&mut self) {
+        self.colony
+            .get_graph_as_mut()
+            .set_previous_assignments(vec![None; self.parameters.num_of_classes]);
+    }
+
     pub fn set_one_hot_pheromone(&mut self, class_id: usize, room_id: usize, period_id: usize) {
         let max_pheromone = self.ceiling_max_pheromone();
         self.colony.set_one_hot_pheromone(
@@ -214,38 +429,93 @@ impl ACOSolver {
     }
 }
 
-pub struct ACOSolverManager {
-    pub solver: Mutex<Option<ACOSolver>>,
-}
-
 #[tauri::command]
 pub fn handle_one_hot_pheromone(
-    solver_manager: tauri::State<'_, ACOSolverManager>,
+    session_manager: tauri::State<'_, SessionManager>,
     class_id: usize,
     room_id: usize,
     period_id: usize,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     info!(
         "called handle_one_hot_pheromone {} {} {}",
         class_id, room_id, period_id
     );
-    let mut managed_solver = solver_manager.solver.lock().unwrap();
-    if let Some(solver) = managed_solver.as_mut() {
-        solver.set_one_hot_pheromone(class_id, room_id, period_id);
-    }
+    let mut session = session_manager.session.lock_state()?;
+    session
+        .get_solver_mut()?
+        .set_one_hot_pheromone(class_id, room_id, period_id);
+    session.touch();
     Ok(())
 }
 
 #[tauri::command]
 pub fn handle_read_cells(
-    solver_manager: tauri::State<'_, ACOSolverManager>,
+    session_manager: tauri::State<'_, SessionManager>,
     cells: Vec<Option<ActiveCell>>,
-) -> Result<(), String> {
+    revision: Option<u64>,
+) -> Result<(), CommandError> {
     info!("called handle_read_cells");
-    let mut managed_solver = solver_manager.solver.lock().unwrap();
-    if let Some(solver) = managed_solver.as_mut() {
-        solver.colony.get_graph_as_mut().load_cells(&cells);
-        return Ok(());
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let solver = session.get_solver_mut()?;
+    //UIから来たセルが今のsolverの範囲に収まっているか、固定を書き換える前に確かめる
+    for cell in cells.iter().flatten() {
+        if cell.class_index >= solver.input.get_classes().len()
+            || cell.room >= solver.parameters.num_of_rooms
+            || cell.period >= solver.parameters.num_of_periods
+        {
+            return Err(CommandError::InvalidArgument(format!(
+                "cell out of range: class {}, room {}, period {}",
+                cell.class_index, cell.room, cell.period
+            )));
+        }
+    }
+    solver
+        .colony
+        .get_graph_as_mut()
+        .load_cells(&cells);
+    session.touch();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn handle_set_class_ordering(
+    session_manager: tauri::State<'_, SessionManager>,
+    class_ordering: ClassOrdering,
+) -> Result<(), CommandError> {
+    info!("called handle_set_class_ordering {:?}", class_ordering);
+    let mut session = session_manager.session.lock_state()?;
+    let solver = session.get_solver_mut()?;
+    let mut parameters = solver.get_parameters();
+    parameters.class_ordering = class_ordering;
+    solver.set_parameters(parameters);
+    if let Ok(parameters) = session.get_parameters_mut() {
+        parameters.class_ordering = class_ordering;
     }
-    return Err("solver is not initialized".to_string());
+    session.touch();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn handle_get_run_events(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<Vec<RunEvent>, CommandError> {
+    info!("called handle_get_run_events");
+    let session = session_manager.session.lock_state()?;
+    Ok(session.get_solver()?.get_run_events().clone())
+}
+
+#[tauri::command]
+pub fn handle_warm_start(
+    session_manager: tauri::State<'_, SessionManager>,
+    strength: f64,
+    revision: Option<u64>,
+) -> Result<(), CommandError> {
+    info!("called handle_warm_start {}", strength);
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let timetable = session.get_timetable()?.clone();
+    session.get_solver_mut()?.warm_start(&timetable, strength)?;
+    session.touch();
+    Ok(())
 }
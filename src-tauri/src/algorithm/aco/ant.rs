@@ -1,13 +1,15 @@
 use super::aco_parameters::AcoParameters;
-use super::graph::{self, Graph};
+use super::class_ordering::{self, ClassOrdering};
+use super::edge_cost::EdgeCost;
+use super::graph::{self, ClassLock, Graph, LockKind};
 use super::violations::{self, Violations};
 use crate::input::class::{self, Class};
 use crate::input::room::Room;
 
 use crate::input::teacher::Teacher;
 use crate::input::Input;
+use crate::algorithm::time_table::resolve::PreviousAssignment;
 use crate::table_editor::Teachers;
-use rand::seq::SliceRandom;
 use std::collections::HashMap;
 use std::collections::{BTreeMap, BTreeSet};
 use std::vec;
@@ -20,6 +22,8 @@ static ABSENT_DAYS_COEF: f64 = 3.0;
 static STRADDLE_DAYS_COEF: f64 = 1.0;
 static COLLECTION_COEF: f64 = 1.0;
 static SEQUENTIAL_FROM_START_COEF: f64 = 4.0;
+static MOVE_ROOM_COEF: f64 = 0.5;
+static MOVE_PERIOD_COEF: f64 = 1.0;
 
 #[derive(Deserialize,Serialize,Clone)]
 pub struct Ant {
@@ -52,7 +56,7 @@ impl Ant {
         };
     }
 
-    fn allocate_classes(
+    pub fn allocate_classes(
         &mut self,
         class_index: usize,
         room_index: usize,
@@ -105,32 +109,184 @@ impl Ant {
         }
     }
 
+    //allocate_classesで置いた授業を外す、corresponding_crpはそのまま
+    pub fn deallocate_classes(&mut self, class_index: usize, graph: &Graph) {
+        if !self.visited_classes[class_index] {
+            return;
+        }
+        let [room_index, period_index] = self.corresponding_crp[class_index];
+        let serial_size = graph.get_class(class_index).serial_size;
+        self.visited_classes[class_index] = false;
+        for i in 0..serial_size {
+            self.visited_roomperiods[room_index][period_index + i] = false;
+        }
+        let class = graph.get_class_ref(class_index);
+        for teacher_index in class.get_teacher_indexes().iter() {
+            if let Some(times) = self.work_periods_each_teachers.get_mut(*teacher_index) {
+                Ant::remove_work_period(times, room_index, period_index, serial_size);
+            }
+        }
+        for student_index in class.get_students_group_indexes().iter() {
+            if let Some(times) = self.work_periods_each_students.get_mut(*student_index) {
+                Ant::remove_work_period(times, room_index, period_index, serial_size);
+            }
+        }
+    }
+
+    fn remove_work_period(
+        times: &mut BTreeMap<usize, Vec<usize>>,
+        room_index: usize,
+        period_index: usize,
+        serial_size: usize,
+    ) {
+        for period in period_index..period_index + serial_size {
+            if let Some(rooms) = times.get_mut(&period) {
+                if let Some(position) = rooms.iter().position(|&x| x == room_index) {
+                    rooms.remove(position);
+                }
+                if rooms.is_empty() {
+                    times.remove(&period);
+                }
+            }
+        }
+    }
+
     pub fn construct_path(&mut self, graph: &Graph) {
-        let shuffled_array = Ant::get_shuffled_array(self.parameters.num_of_classes);
+        let class_order = self.get_class_order(graph);
         self.work_periods_each_teachers =
             vec![BTreeMap::new(); self.parameters.num_of_teachers as usize];
         self.work_periods_each_students =
             vec![BTreeMap::new(); self.parameters.num_of_students as usize];
         //preallocate locked classes
-        for v in shuffled_array.iter() {
-            if let Some(to) = graph.get_classes_is_locked(*v) {
-                self.allocate_classes(*v, to.0, to.1, graph);
+        for v in class_order.iter() {
+            if let Some(lock) = graph.get_classes_is_locked(*v) {
+                if lock.kind == LockKind::Both {
+                    self.allocate_classes(*v, lock.room, lock.period, graph);
+                }
+            }
+        }
+        //部屋かコマだけ固定された授業は、固定された範囲で先に置く
+        for v in class_order.iter() {
+            if !self.visited_classes[*v] && graph.get_classes_is_locked(*v).is_some() {
+                self.allocate_with_pheromone(*v, graph);
             }
         }
         //allocate with pheromone
-        for v in shuffled_array.iter() {
+        if self.parameters.class_ordering == ClassOrdering::SaturationDegree {
+            while let Some(v) = self.select_most_saturated_class(&class_order, graph) {
+                self.allocate_with_pheromone(v, graph);
+            }
+            return;
+        }
+        for v in class_order.iter() {
+            if self.visited_classes[*v] {
+                continue;
+            }
+            self.allocate_with_pheromone(*v, graph);
+        }
+    }
+
+    fn allocate_with_pheromone(&mut self, v: usize, graph: &Graph) {
+        let (to_vertex, to_period) = self.calc_prob_from_v(v, graph);
+        let to: [usize; 2];
+        if rand::random::<f64>() < self.parameters.ant_prob_random {
+            to = to_vertex[rand::random::<usize>() % to_vertex.len()];
+        } else {
+            let random_p = rand::random::<f64>();
+            to = to_vertex[to_period.iter().position(|&x| x > random_p).unwrap()];
+        }
+        self.allocate_classes(v, to[0], to[1], graph);
+    }
+
+    fn get_class_order(&self, graph: &Graph) -> Vec<usize> {
+        let num_of_classes = self.parameters.num_of_classes;
+        match self.parameters.class_ordering {
+            ClassOrdering::Random => class_ordering::get_shuffled_order(num_of_classes),
+            //SaturationDegreeでは同じ飽和度のときの順番として使う
+            ClassOrdering::MostConstrainedFirst | ClassOrdering::SaturationDegree => {
+                class_ordering::get_most_constrained_order(num_of_classes, graph)
+            }
+            ClassOrdering::PheromoneLearned => class_ordering::get_pheromone_learned_order(
+                num_of_classes,
+                graph,
+                self.parameters.alpha,
+            ),
+        }
+    }
+
+    // the number of periods already occupied by the teachers or groups of the class
+    fn calc_saturation_degree(&self, v: usize, graph: &Graph) -> usize {
+        let class = graph.get_class_ref(v);
+        let mut busy_periods = BTreeSet::<usize>::new();
+        for id in class.get_teacher_indexes().iter() {
+            if let Some(times) = self.work_periods_each_teachers.get(*id) {
+                busy_periods.extend(times.keys());
+            }
+        }
+        for id in class.get_students_group_indexes().iter() {
+            if let Some(times) = self.work_periods_each_students.get(*id) {
+                busy_periods.extend(times.keys());
+            }
+        }
+        busy_periods.len()
+    }
+
+    fn select_most_saturated_class(&self, class_order: &Vec<usize>, graph: &Graph) -> Option<usize> {
+        let mut res: Option<(usize, usize)> = None;
+        for v in class_order.iter() {
             if self.visited_classes[*v] {
                 continue;
             }
-            let (to_vertex, to_period) = self.calc_prob_from_v(*v, graph);
-            let to: [usize; 2];
-            if rand::random::<f64>() < self.parameters.ant_prob_random {
-                to = to_vertex[rand::random::<usize>() % to_vertex.len()];
-            } else {
-                let random_p = rand::random::<f64>();
-                to = to_vertex[to_period.iter().position(|&x| x > random_p).unwrap()];
+            let saturation = self.calc_saturation_degree(*v, graph);
+            if let Some((_, max_saturation)) = res {
+                if saturation <= max_saturation {
+                    continue;
+                }
+            }
+            res = Some((*v, saturation));
+        }
+        res.map(|(v, _)| v)
+    }
+
+    pub fn set_parameters(&mut self, parameters: AcoParameters) {
+        self.parameters = parameters;
+    }
+
+    // how many clashes each class is involved in
+    pub fn count_violations_each_class(&self, graph: &Graph) -> Vec<usize> {
+        let mut counts = vec![0; self.corresponding_crp.len()];
+        for class_id in 0..self.corresponding_crp.len() {
+            let [room, period] = self.corresponding_crp[class_id];
+            let class = graph.get_class_ref(class_id);
+            let serial_size = class.serial_size;
+            if graph.get_room_ref(room).get_capacity() < class.get_num_of_students() {
+                counts[class_id] += 1;
+            }
+            if Ant::overlaps_unavailable_periods(class, period) {
+                counts[class_id] += 1;
+            }
+            for i in 0..serial_size {
+                for id in class.get_teacher_indexes().iter() {
+                    if let Some(time) = self.work_periods_each_teachers[*id].get(&(period + i)) {
+                        counts[class_id] += time.len() - 1;
+                    }
+                }
+                for id in class.get_students_group_indexes().iter() {
+                    if let Some(time) = self.work_periods_each_students[*id].get(&(period + i)) {
+                        counts[class_id] += time.len() - 1;
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    pub fn update_next_order_pheromone(&self, graph: &mut Graph) {
+        let q = self.parameters.q;
+        for (class_id, count) in self.count_violations_each_class(graph).iter().enumerate() {
+            if *count > 0 {
+                graph.add_next_order_pheromone(class_id, q * (*count as f64));
             }
-            self.allocate_classes(*v, to[0], to[1], graph);
         }
     }
 
@@ -224,16 +380,35 @@ impl Ant {
         for class_id in 0..self.corresponding_crp.len() {
             let [room, period] = self.corresponding_crp[class_id];
             let absent_days = self.calc_absent_days(
-                &graph.get_class_ref(class_id).get_teacher_indexes(),
+                graph.get_class_ref(class_id),
                 graph.get_teachers_ref(),
             );
             if (absent_days.contains(&period)) {
                 length[period] += ABSENT_DAYS_COEF;
             }
         }
+        for class_id in 0..self.corresponding_crp.len() {
+            let [room, period] = self.corresponding_crp[class_id];
+            length[period] +=
+                Ant::calc_move_cost(graph.get_previous_assignment(class_id), room, period);
+        }
         length
     }
 
+    //再計画時に変更前の配置から動いた分のコスト
+    fn calc_move_cost(previous_assignment: Option<PreviousAssignment>, room: usize, period: usize) -> f64 {
+        let mut cost = 0.0;
+        if let Some(previous) = previous_assignment {
+            if previous.room != Some(room) {
+                cost += MOVE_ROOM_COEF;
+            }
+            if previous.period != period {
+                cost += MOVE_PERIOD_COEF;
+            }
+        }
+        cost
+    }
+
     // straddle days
     fn calc_all_path_length_each_room(&self, graph: &Graph) -> Vec<f64> {
         let mut length = vec![0.0; self.parameters.num_of_rooms as usize];
@@ -293,13 +468,23 @@ impl Ant {
         for class_id in 0..self.corresponding_crp.len() {
             let [room, period] = self.corresponding_crp[class_id];
             let absent_days = self.calc_absent_days(
-                &graph.get_class_ref(class_id).get_teacher_indexes(),
+                graph.get_class_ref(class_id),
                 graph.get_teachers_ref(),
             );
             if (absent_days.contains(&period)) {
                 counts[period] += 1;
             }
         }
+        //置けないコマは連続授業の途中にかかっても違反にする
+        for class_id in 0..self.corresponding_crp.len() {
+            let [_, period] = self.corresponding_crp[class_id];
+            let class = graph.get_class_ref(class_id);
+            for i in 1..class.serial_size {
+                if class.unavailable_periods.contains(&(period + i)) {
+                    counts[period + i] += 1;
+                }
+            }
+        }
         counts
     }
     
@@ -349,15 +534,32 @@ impl Ant {
         res
     }
 
-    fn calc_absent_days(
-        &self,
-        teacher_indices: &Vec<usize>,
-        teachers: &Vec<Teacher>,
-    ) -> BTreeSet<usize> {
+    fn calc_absent_days(&self, class: &Class, teachers: &Vec<Teacher>) -> BTreeSet<usize> {
         let mut res = BTreeSet::new();
-        for teacher_id in teacher_indices.iter() {
+        for teacher_id in class.get_teacher_indexes().iter() {
             res.extend(teachers[*teacher_id].absent_days.clone());
         }
+        res.extend(class.unavailable_periods.iter());
+        res
+    }
+
+    //連続授業のどこかが置けないコマにかかるか
+    fn overlaps_unavailable_periods(class: &Class, period: usize) -> bool {
+        (period..period + class.serial_size).any(|p| class.unavailable_periods.contains(&p))
+    }
+
+    //空いているかに関係なく、固定された範囲で置ける部屋とコマ
+    fn calc_locked_room_periods(&self, class_period_length: usize, lock: &ClassLock) -> Vec<[usize; 2]> {
+        let mut res = Vec::new();
+        for room in 0..self.parameters.num_of_rooms {
+            for period in 0..self.parameters.num_of_periods {
+                if period + class_period_length <= self.parameters.num_of_periods
+                    && lock.allows(room, period)
+                {
+                    res.push([room, period]);
+                }
+            }
+        }
         res
     }
 
@@ -369,7 +571,25 @@ impl Ant {
         let beta = self.parameters.beta;
         let class_period_length = graph.get_class(v).serial_size;
 
-        for [room, period] in self.calc_allocatable_room_periods(class_period_length, graph) {
+        let mut candidates = self.calc_allocatable_room_periods(class_period_length, graph);
+        //固定された範囲に空きがなければ、Bothと同じく埋まっていても固定された範囲に置いて違反として見せる
+        if let Some(lock) = graph.get_classes_is_locked(v) {
+            candidates.retain(|[room, period]| lock.allows(*room, *period));
+            if candidates.is_empty() {
+                candidates = self.calc_locked_room_periods(class_period_length, &lock);
+            }
+        }
+        //置けないコマは候補から外す。外すと置き場がなくなる場合だけ残して違反として見せる
+        let available_candidates = candidates
+            .iter()
+            .filter(|[_, period]| !Ant::overlaps_unavailable_periods(graph.get_class_ref(v), *period))
+            .cloned()
+            .collect::<Vec<[usize; 2]>>();
+        if !available_candidates.is_empty() {
+            candidates = available_candidates;
+        }
+
+        for [room, period] in candidates {
             let pheromone = graph.get_pheromone(v, room, period);
 
             let heuristics = 1.0
@@ -377,11 +597,12 @@ impl Ant {
                     graph.get_room_ref(room),
                     graph.get_class_ref(v),
                     &self.calc_absent_days(
-                        &graph.get_class_ref(v).get_teacher_indexes(),
+                        graph.get_class_ref(v),
                         graph.get_teachers_ref(),
                     ),
                     period as usize,
                     self.parameters.size_of_frame,
+                    graph.get_previous_assignment(v),
                 );
             //ここを足し算か掛け算に変更
             let pre_normalized_value = pheromone.powf(alpha) * heuristics.powf(beta);
@@ -419,11 +640,12 @@ impl Ant {
                         graph.get_room_ref(room),
                         graph.get_class_ref(v),
                         &self.calc_absent_days(
-                            &graph.get_class_ref(v).get_teacher_indexes(),
+                            graph.get_class_ref(v),
                             graph.get_teachers_ref(),
                         ),
                         period as usize,
                         self.parameters.size_of_frame,
+                        graph.get_previous_assignment(v),
                     );
                 let pheromone = pre_pheromone.powf(alpha) * heuristics.powf(beta);
                 if v == 0 {}
@@ -446,18 +668,41 @@ impl Ant {
         absent_days: &BTreeSet<usize>,
         period: usize,
         size_of_frame: usize,
+        previous_assignment: Option<PreviousAssignment>,
     ) -> f64 {
-        let mut edge_length = 1.0;
+        self.calc_edge_cost(
+            room,
+            class,
+            absent_days,
+            period,
+            size_of_frame,
+            previous_assignment,
+        )
+        .get_edge_length()
+    }
+
+    fn calc_edge_cost(
+        &self,
+        room: &Room,
+        class: &Class,
+        absent_days: &BTreeSet<usize>,
+        period: usize,
+        size_of_frame: usize,
+        previous_assignment: Option<PreviousAssignment>,
+    ) -> EdgeCost {
+        let mut cost = EdgeCost::default();
+        //moved from the previous assignment
+        cost.move_cost = Ant::calc_move_cost(previous_assignment, room.index, period);
         //capacity violation
         if class.get_num_of_students() > room.get_capacity() {
-            edge_length += CAP_COEF;
+            cost.capacity = CAP_COEF;
         }
         //students violation
         for id in class.get_students_group_indexes().iter() {
             if let Some(times) = self.work_periods_each_students.get(*id as usize) {
                 if let Some(time) = times.get(&(period as usize)) {
                     let ftime = (*time).len() as f64;
-                    edge_length += (ftime )* STUDENT_COEF;
+                    cost.students += (ftime )* STUDENT_COEF;
                 }
             }
         }
@@ -466,19 +711,19 @@ impl Ant {
             if let Some(times) = self.work_periods_each_teachers.get(*id as usize) {
                 if let Some(time) = times.get(&(period as usize)) {
                     let ftime = (*time).len() as f64;
-                    edge_length += (ftime)  * TEACHER_COEF;
+                    cost.teachers += (ftime)  * TEACHER_COEF;
                 }
             }
         }
         //teacher abset days violation
         if absent_days.contains(&period) {
-            edge_length += ABSENT_DAYS_COEF;
+            cost.absent_days = ABSENT_DAYS_COEF;
         }
         //straddle days violation
         if (period % self.parameters.num_of_day_lengths) + class.serial_size
             > self.parameters.num_of_day_lengths
         {
-            edge_length += STRADDLE_DAYS_COEF;
+            cost.straddle_days = STRADDLE_DAYS_COEF;
         }
         //collection violation
         {
@@ -489,22 +734,52 @@ impl Ant {
                     count_in_frame += 1;
                 }
             }
-            edge_length += (size_of_frame - count_in_frame) as f64 * COLLECTION_COEF;
+            cost.collection = (size_of_frame - count_in_frame) as f64 * COLLECTION_COEF;
             if count_in_frame == 0 {
-                edge_length += (size_of_frame) as f64 * COLLECTION_COEF;
+                cost.collection += (size_of_frame) as f64 * COLLECTION_COEF;
             }
         }
-        edge_length
+        cost
     }
 
-    fn get_shuffled_array(num_of_classes: usize) -> Vec<usize> {
-        let mut array = Vec::new();
-        for i in 0..num_of_classes as usize {
-            array.push(i);
+    //既存の割り当てからantを復元する
+    pub fn from_corresponding_crp(
+        parameters: AcoParameters,
+        corresponding_crp: &Vec<[usize; 2]>,
+        graph: &Graph,
+    ) -> Ant {
+        let mut ant = Ant::new(parameters);
+        for (class_index, &[room_index, period_index]) in corresponding_crp.iter().enumerate() {
+            ant.allocate_classes(class_index, room_index, period_index, graph);
         }
-        let mut rng = rand::thread_rng();
-        array.shuffle(&mut rng);
-        array
+        ant
+    }
+
+    //Noneの授業は置かずにantを復元する
+    pub fn from_assignments(
+        parameters: AcoParameters,
+        assignments: &Vec<Option<[usize; 2]>>,
+        graph: &Graph,
+    ) -> Ant {
+        let mut ant = Ant::new(parameters);
+        for (class_index, assignment) in assignments.iter().enumerate() {
+            if let Some([room_index, period_index]) = assignment {
+                ant.allocate_classes(class_index, *room_index, *period_index, graph);
+            }
+        }
+        ant
+    }
+
+    //授業vを(room, period)に置いたときのcalc_edge_lengthの内訳
+    pub fn calc_edge_cost_of(&self, v: usize, room: usize, period: usize, graph: &Graph) -> EdgeCost {
+        self.calc_edge_cost(
+            graph.get_room_ref(room),
+            graph.get_class_ref(v),
+            &self.calc_absent_days(graph.get_class_ref(v), graph.get_teachers_ref()),
+            period,
+            self.parameters.size_of_frame,
+            graph.get_previous_assignment(v),
+        )
     }
 
     pub fn reset_ant(&mut self) {
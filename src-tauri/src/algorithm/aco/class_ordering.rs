@@ -0,0 +1,70 @@
+use super::graph::Graph;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+//antがpathを構築する際にclassを訪問する順番
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ClassOrdering {
+    #[default]
+    Random,
+    MostConstrainedFirst,
+    SaturationDegree,
+    PheromoneLearned,
+}
+
+pub fn get_shuffled_order(num_of_classes: usize) -> Vec<usize> {
+    let mut array = (0..num_of_classes).collect::<Vec<usize>>();
+    let mut rng = rand::thread_rng();
+    array.shuffle(&mut rng);
+    array
+}
+
+// larger serial_size, more teachers/groups, fewer room candidates, more students first
+// ties are broken randomly
+pub fn get_most_constrained_order(num_of_classes: usize, graph: &Graph) -> Vec<usize> {
+    let mut array = get_shuffled_order(num_of_classes);
+    array.sort_by(|a, b| {
+        let a = graph.get_class_ref(*a);
+        let b = graph.get_class_ref(*b);
+        b.serial_size
+            .cmp(&a.serial_size)
+            .then(
+                (b.teacher_indexes.len() + b.students_group_indexes.len())
+                    .cmp(&(a.teacher_indexes.len() + a.students_group_indexes.len())),
+            )
+            .then(
+                a.room_candidates_indexes
+                    .len()
+                    .cmp(&b.room_candidates_indexes.len()),
+            )
+            .then(b.num_of_students.cmp(&a.num_of_students))
+    });
+    array
+}
+
+// roulette selection without replacement weighted by the order pheromone,
+// so classes which often clash are visited earlier
+pub fn get_pheromone_learned_order(num_of_classes: usize, graph: &Graph, alpha: f64) -> Vec<usize> {
+    let mut rest = (0..num_of_classes).collect::<Vec<usize>>();
+    let mut weights = rest
+        .iter()
+        .map(|&i| graph.get_order_pheromone(i).powf(alpha))
+        .collect::<Vec<f64>>();
+    let mut array = Vec::with_capacity(num_of_classes);
+    while !rest.is_empty() {
+        let sum_weight = weights.iter().sum::<f64>();
+        let mut random_p = rand::random::<f64>() * sum_weight;
+        let mut selected = rest.len() - 1;
+        for (i, w) in weights.iter().enumerate() {
+            if random_p < *w {
+                selected = i;
+                break;
+            }
+            random_p -= w;
+        }
+        array.push(rest.swap_remove(selected));
+        weights.swap_remove(selected);
+    }
+    array
+}
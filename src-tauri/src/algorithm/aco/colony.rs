@@ -1,5 +1,6 @@
 use super::aco_parameters::AcoParameters;
 use super::ant::Ant;
+use super::class_ordering::ClassOrdering;
 use super::graph::Graph;
 use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Clone)]
@@ -53,6 +54,9 @@ impl Colony {
             ant.update_next_pheromone(&mut self.graph);
             //制約統一
             //ant.update_next_pheromone_kenekayoro(&mut self.graph);
+            if self.parameters.class_ordering == ClassOrdering::PheromoneLearned {
+                ant.update_next_order_pheromone(&mut self.graph);
+            }
         }
     }
     pub fn reset_colony(&mut self) {
@@ -61,8 +65,8 @@ impl Colony {
         }
     }
 
-    pub fn reset_pheromone(&mut self) {
-        self.graph.reset_graph_when_stagnation();
+    pub fn reset_pheromone(&mut self, ratio: f64) {
+        self.graph.reset_graph_when_stagnation(ratio);
     }
 
     pub fn update_next_pheromone(&mut self) {
@@ -97,6 +101,17 @@ impl Colony {
                 }
             }
         }
+        if self.parameters.class_ordering == ClassOrdering::PheromoneLearned {
+            self.graph.update_order_pheromone(rou, tau_min, tau_max);
+        }
+    }
+
+    pub fn set_parameters(&mut self, parameters: AcoParameters) {
+        for ant in self.ants.iter_mut() {
+            ant.set_parameters(parameters.clone());
+        }
+        self.graph.set_parameters(parameters.clone());
+        self.parameters = parameters;
     }
 
     pub fn get_graph(&self) -> &Graph {
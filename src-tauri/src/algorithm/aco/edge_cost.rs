@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+//Ant::calc_edge_lengthの内訳、edge_length = 1.0 + 各項目の和
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeCost {
+    pub move_cost: f64,
+    pub capacity: f64,
+    pub students: f64,
+    pub teachers: f64,
+    pub absent_days: f64,
+    pub straddle_days: f64,
+    pub collection: f64,
+}
+
+impl EdgeCost {
+    pub fn get_edge_length(&self) -> f64 {
+        1.0 + self.move_cost
+            + self.capacity
+            + self.students
+            + self.teachers
+            + self.absent_days
+            + self.straddle_days
+            + self.collection
+    }
+}
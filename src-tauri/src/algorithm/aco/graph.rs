@@ -1,6 +1,7 @@
 use super::super::time_table::cell::Cell;
 use super::aco_parameters::AcoParameters;
 use crate::algorithm::time_table::cell::ActiveCell;
+use crate::algorithm::time_table::resolve::PreviousAssignment;
 use crate::input::class::{self, Class};
 use crate::input::room::Room;
 use crate::input::teacher::{self, Teacher};
@@ -15,10 +16,48 @@ pub struct Edge {
     next_pheromone: f64,
 }
 
+//授業の固定の仕方
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LockKind {
+    //部屋とコマの両方
+    Both,
+    //コマだけ固定して部屋はsolverが選ぶ
+    PeriodOnly,
+    //部屋だけ固定してコマはsolverが選ぶ
+    RoomOnly,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassLock {
+    pub kind: LockKind,
+    pub room: usize,
+    pub period: usize,
+}
+
+impl ClassLock {
+    pub fn allows(&self, room: usize, period: usize) -> bool {
+        match self.kind {
+            LockKind::Both => room == self.room && period == self.period,
+            LockKind::PeriodOnly => period == self.period,
+            LockKind::RoomOnly => room == self.room,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Graph {
     edges: Vec<Vec<Vec<Edge>>>,
-    classes_is_locked: Vec<Option<(usize, usize)>>,
+    classes_is_locked: Vec<Option<ClassLock>>,
+    //class_ordering: PheromoneLearned で使う訪問順のフェロモン
+    #[serde(default)]
+    order_pheromones: Vec<f64>,
+    #[serde(default)]
+    next_order_pheromones: Vec<f64>,
+    //再計画時の変更前の配置
+    #[serde(default)]
+    previous_assignments: Vec<Option<PreviousAssignment>>,
     num_of_classes: usize,
     num_of_rooms: usize,
     num_of_periods: usize,
@@ -55,9 +94,14 @@ impl Graph {
             num_of_classes as usize
         ];
         let classes_is_locked = vec![None; num_of_classes as usize];
+        let order_pheromones = vec![parameters.q; num_of_classes as usize];
+        let next_order_pheromones = vec![0.0; num_of_classes as usize];
         let mut res = Graph {
             edges: edges,
             classes_is_locked,
+            order_pheromones,
+            next_order_pheromones,
+            previous_assignments: vec![None; num_of_classes as usize],
             num_of_classes: parameters.num_of_classes,
             num_of_rooms: parameters.num_of_rooms,
             num_of_periods: parameters.num_of_periods,
@@ -112,9 +156,22 @@ impl Graph {
         }
     }
 
-    pub fn get_classes_is_locked(&self, class_index: usize) -> Option<(usize, usize)> {
+    pub fn set_previous_assignments(&mut self, previous_assignments: Vec<Option<PreviousAssignment>>) {
+        self.previous_assignments = previous_assignments;
+    }
+    pub fn get_previous_assignment(&self, class_index: usize) -> Option<PreviousAssignment> {
+        return self.previous_assignments.get(class_index).copied().flatten();
+    }
+
+    pub fn get_classes_is_locked(&self, class_index: usize) -> Option<ClassLock> {
         return self.classes_is_locked[class_index];
     }
+    pub fn get_all_classes_is_locked(&self) -> &Vec<Option<ClassLock>> {
+        return &self.classes_is_locked;
+    }
+    pub fn set_classes_is_locked(&mut self, class_index: usize, to: Option<ClassLock>) {
+        self.classes_is_locked[class_index] = to;
+    }
 
     pub fn reset_graph(&mut self) {
         for i in 0..self.num_of_classes as usize {
@@ -124,15 +181,78 @@ impl Graph {
                 }
             }
         }
+        self.next_order_pheromones = vec![0.0; self.num_of_classes as usize];
     }
-    pub fn reset_graph_when_stagnation(&mut self) {
+    //ratioが1.0なら初期値に戻し、それ未満なら初期値との内分で部分的に戻す
+    pub fn reset_graph_when_stagnation(&mut self, ratio: f64) {
+        let ratio = ratio.clamp(0.0, 1.0);
+        let q = self.parameters.q;
         for i in 0..self.num_of_classes as usize {
             for j in 0..self.num_of_rooms as usize {
                 for k in 0..self.num_of_periods as usize {
-                    self.edges[i][j][k].pheromone = self.parameters.q;
+                    self.edges[i][j][k].pheromone =
+                        self.edges[i][j][k].pheromone * (1.0 - ratio) + q * ratio;
                 }
             }
         }
+        self.order_pheromones = (0..self.num_of_classes)
+            .map(|i| self.get_order_pheromone(i) * (1.0 - ratio) + q * ratio)
+            .collect();
+    }
+
+    // average number of (room, period) per class whose pheromone is above
+    // min + lambda * (max - min) of that class
+    pub fn calc_branching_factor(&self, lambda: f64) -> f64 {
+        if self.num_of_classes == 0 {
+            return 0.0;
+        }
+        let mut sum_branches = 0;
+        for i in 0..self.num_of_classes as usize {
+            let mut min_pheromone = f64::MAX;
+            let mut max_pheromone = f64::MIN;
+            for j in 0..self.num_of_rooms as usize {
+                for k in 0..self.num_of_periods as usize {
+                    min_pheromone = min_pheromone.min(self.edges[i][j][k].pheromone);
+                    max_pheromone = max_pheromone.max(self.edges[i][j][k].pheromone);
+                }
+            }
+            let threshold = min_pheromone + lambda * (max_pheromone - min_pheromone);
+            for j in 0..self.num_of_rooms as usize {
+                for k in 0..self.num_of_periods as usize {
+                    if self.edges[i][j][k].pheromone >= threshold {
+                        sum_branches += 1;
+                    }
+                }
+            }
+        }
+        sum_branches as f64 / self.num_of_classes as f64
+    }
+
+    pub fn set_parameters(&mut self, parameters: AcoParameters) {
+        self.parameters = parameters;
+    }
+
+    pub fn get_order_pheromone(&self, class_index: usize) -> f64 {
+        //古いdumpには訪問順のフェロモンが無いので初期値として扱う
+        if let Some(pheromone) = self.order_pheromones.get(class_index) {
+            return *pheromone;
+        }
+        return self.parameters.q;
+    }
+    pub fn add_next_order_pheromone(&mut self, class_index: usize, pheromone: f64) {
+        if self.next_order_pheromones.len() != self.num_of_classes {
+            self.next_order_pheromones = vec![0.0; self.num_of_classes];
+        }
+        self.next_order_pheromones[class_index] += pheromone;
+    }
+    pub fn update_order_pheromone(&mut self, rou: f64, tau_min: f64, tau_max: f64) {
+        let mut order_pheromones = Vec::with_capacity(self.num_of_classes);
+        for i in 0..self.num_of_classes {
+            let next = self.next_order_pheromones.get(i).copied().unwrap_or(0.0);
+            let pheromone = self.get_order_pheromone(i) * rou + next;
+            order_pheromones.push(pheromone.clamp(tau_min, tau_max));
+        }
+        self.order_pheromones = order_pheromones;
     }
     pub fn get_pheromone(&self, class_index: usize, room_index: usize, period_index: usize) -> f64 {
         return self.edges[class_index][room_index][period_index].pheromone;
@@ -210,20 +330,33 @@ impl Graph {
             self.parameters.q * max_pheromone;
     }
 
-    pub fn load_cells(&mut self, cells: &Vec<Option<ActiveCell>>) {
-        for (i, cell) in cells.iter().enumerate() {
-            if let Some(active_cell) = cell {
-                if let Some(is_locked) = active_cell.is_locked {
-                    if is_locked {
-                        self.classes_is_locked[active_cell.class_index] =
-                            Some((active_cell.room, active_cell.period));
-                    } else {
-                        self.classes_is_locked[active_cell.class_index] = None;
-                    }
+    //strengthが1.0ならset_one_hot_pheromoneと同じ、0.0なら何もしない
+    pub fn seed_pheromone(
+        &mut self,
+        class_index: usize,
+        room_index: usize,
+        period_index: usize,
+        strength: f64,
+        min_pheromone: f64,
+        max_pheromone: f64,
+    ) {
+        let strength = strength.clamp(0.0, 1.0);
+        for j in 0..self.num_of_rooms as usize {
+            for k in 0..self.num_of_periods as usize {
+                let target = if j == room_index && k == period_index {
+                    self.parameters.q * max_pheromone
                 } else {
-                    self.classes_is_locked[active_cell.class_index] = None;
-                }
+                    min_pheromone
+                };
+                self.edges[class_index][j][k].pheromone =
+                    self.edges[class_index][j][k].pheromone * (1.0 - strength) + target * strength;
             }
         }
     }
+
+    pub fn load_cells(&mut self, cells: &Vec<Option<ActiveCell>>) {
+        for active_cell in cells.iter().flatten() {
+            self.classes_is_locked[active_cell.class_index] = active_cell.get_lock();
+        }
+    }
 }
@@ -0,0 +1,27 @@
+use super::stopping_criteria::StopReason;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum StagnationReason {
+    //super_antがsuper_not_change世代の間更新されなかった
+    NoImprovement { generations: usize },
+    //フェロモンの平均分岐数がmin_branching_factorを下回った
+    LowBranchingFactor { branching_factor: f64 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RunEvent {
+    Restart {
+        generation: usize,
+        reason: StagnationReason,
+        //1.0で全リセット、それ未満は初期値との内分による部分リセット
+        ratio: f64,
+    },
+    Stop {
+        generation: usize,
+        reason: StopReason,
+        elapsed_secs: f64,
+    },
+}
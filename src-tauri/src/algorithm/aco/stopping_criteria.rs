@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+static VALUE_OPTIONS: [&str; 4] = [
+    "--time-limit",
+    "--max-generations",
+    "--target-score",
+    "--no-improvement",
+];
+
+//いずれかの条件を満たした時点で停止する
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoppingCriteria {
+    pub time_limit_secs: Option<f64>,
+    pub max_generations: Option<usize>,
+    pub target_score: Option<f64>,
+    #[serde(default)]
+    pub zero_hard_violations: bool,
+    pub no_improvement_generations: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum StopReason {
+    TimeLimit,
+    GenerationLimit,
+    TargetScore,
+    ZeroHardViolations,
+    NoImprovement,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    pub reason: StopReason,
+    pub generations: usize,
+    pub elapsed_secs: f64,
+    pub score: f64,
+    pub hard_violations: usize,
+}
+
+impl StoppingCriteria {
+    //目標値だけでは終わらない可能性があるので、時間・世代・改善なしのどれかを必須にする
+    pub fn is_bounded(&self) -> bool {
+        self.time_limit_secs.is_some()
+            || self.max_generations.is_some()
+            || self.no_improvement_generations.is_some()
+    }
+
+    pub fn check(
+        &self,
+        elapsed: Duration,
+        generations: usize,
+        score: f64,
+        hard_violations: usize,
+        generations_without_improvement: usize,
+    ) -> Option<StopReason> {
+        if let Some(target_score) = self.target_score {
+            if score <= target_score {
+                return Some(StopReason::TargetScore);
+            }
+        }
+        if self.zero_hard_violations && hard_violations == 0 {
+            return Some(StopReason::ZeroHardViolations);
+        }
+        if let Some(time_limit_secs) = self.time_limit_secs {
+            if elapsed.as_secs_f64() >= time_limit_secs {
+                return Some(StopReason::TimeLimit);
+            }
+        }
+        if let Some(max_generations) = self.max_generations {
+            if generations >= max_generations {
+                return Some(StopReason::GenerationLimit);
+            }
+        }
+        if let Some(no_improvement_generations) = self.no_improvement_generations {
+            if generations_without_improvement >= no_improvement_generations {
+                return Some(StopReason::NoImprovement);
+            }
+        }
+        None
+    }
+
+    // --time-limit <secs> --max-generations <n> --target-score <score>
+    // --zero-violations --no-improvement <n>
+    pub fn parse_args(args: &[String]) -> Result<StoppingCriteria, String> {
+        let mut res = StoppingCriteria::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--zero-violations" {
+                res.zero_hard_violations = true;
+                continue;
+            }
+            if !VALUE_OPTIONS.contains(&arg.as_str()) {
+                return Err(format!("unknown option: {}", arg));
+            }
+            let value = iter.next().ok_or(format!("missing value for {}", arg))?;
+            let invalid = format!("invalid value for {}: {}", arg, value);
+            match arg.as_str() {
+                "--time-limit" => {
+                    res.time_limit_secs = Some(value.parse().map_err(|_| invalid)?);
+                }
+                "--max-generations" => {
+                    res.max_generations = Some(value.parse().map_err(|_| invalid)?);
+                }
+                "--target-score" => {
+                    res.target_score = Some(value.parse().map_err(|_| invalid)?);
+                }
+                "--no-improvement" => {
+                    res.no_improvement_generations = Some(value.parse().map_err(|_| invalid)?);
+                }
+                _ => {}
+            }
+        }
+        Ok(res)
+    }
+}
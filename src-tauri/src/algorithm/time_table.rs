@@ -1,29 +1,40 @@
 //変換を作る
+pub mod bulk_lock;
 pub mod cell;
+pub mod explanation;
+pub mod history;
+pub mod moves;
+pub mod projection;
+pub mod resolve;
+pub mod snapshot;
+pub mod suggestion;
+pub mod weak_placement;
 
-use crate::input;
 use crate::input::class;
 use crate::input::class::Class;
-use cell::ActiveCell;
+use cell::{ActiveCell, CellPosition};
+use history::{EditKind, HistoryState};
+use moves::{apply_moves, plan_move};
+use weak_placement::{analyze_weak_placements, WeakPlacement, WeakPlacementConfig};
 use cell::BlankCell;
 use core::str;
 use core::time;
 use std::cmp::min;
+use std::collections::BTreeSet;
 use std::error::Error;
 use std::os::unix::raw::time_t;
 use std::result::Result;
-use std::fs::File;
-use std::io::Read;
-use std::io::Write;
-use std::sync::Mutex;
 use super::aco;
 use super::aco::aco_solver::ACOSolver;
-use super::aco::aco_solver::ACOSolverManager;
+use super::aco::graph::LockKind;
 use super::aco::violations::CellsViolation;
 use super::aco::violations::Violations;
 use crate::input::room::Room;
+use crate::input::Input;
 use serde::{Deserialize, Serialize};
-use log::info;
+use log::{info, warn};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -32,9 +43,11 @@ pub struct TimeTable {
     pub process_table: Vec<Vec<Option<Class>>>,
     pub room_size: usize,
     pub period_size: usize,
+    //UIに返したときのsessionのrevision
+    #[serde(default)]
+    pub revision: u64,
 }
 //TODO timeTableに関する操作を抽象化して、それぞれの操作を関数で行う。
-//座標とindexを連動させるべきではない
 
 impl TimeTable {
     pub fn new(room_size: usize, period_size: usize, class_size: usize) -> TimeTable {
@@ -55,6 +68,7 @@ impl TimeTable {
             process_table,
             room_size,
             period_size,
+            revision: 0,
         }
     }
 
@@ -62,6 +76,40 @@ impl TimeTable {
         self.process_table[room][period].clone()
     }
 
+    pub fn get_class_index_by_id(&self, class_id: usize) -> Option<usize> {
+        self.class_list
+            .iter()
+            .position(|x| x.as_ref().map(|x| x.id == class_id).unwrap_or(false))
+    }
+
+    //以前は座標から計算したidを使っていたので、読み込んだ時間割はclass idに振り直す
+    pub fn refresh_cell_ids(&mut self, input: &Input) {
+        for cell in self.class_list.iter_mut().flatten() {
+            if let Some(class) = input.get_classes().get(cell.class_index) {
+                cell.id = class.id;
+            }
+        }
+    }
+
+    //移動先に日付をはみ出さず、他の授業と重ならずに置けるか
+    pub fn is_movable(&self, class_index: usize, to: CellPosition) -> bool {
+        let size = match &self.class_list[class_index] {
+            Some(cell) => cell.size.unwrap_or(1),
+            None => return false,
+        };
+        if to.room >= self.room_size || to.period + size > self.period_size {
+            return false;
+        }
+        for period in to.period..(to.period + size) {
+            if let Some(class) = self.process_table[to.room][period].as_ref() {
+                if class.index != class_index {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
     pub fn add_class(
         &mut self,
         room: usize,
@@ -74,13 +122,14 @@ impl TimeTable {
             self.process_table[room][period + i] = Some(class.clone());
         }
         let mut is_locked = None;
-        if let Some(_) = solver.colony.get_graph().get_classes_is_locked(class.index) {
+        let mut lock_kind = None;
+        if let Some(lock) = solver.colony.get_graph().get_classes_is_locked(class.index) {
             is_locked = Some(true);
+            lock_kind = Some(lock.kind);
         }
-        let id = room * self.period_size + period;
         let tearchers = solver.input.get_teachers();
         self.class_list[class.index] = Some(ActiveCell {
-            id: id + self.period_size * self.room_size,
+            id: class.id,
             period: period,
             room: room,
             class_index: class.index,
@@ -102,10 +151,11 @@ impl TimeTable {
             student_size: Some(class.num_of_students),
             color: color,
             is_locked: is_locked,
+            lock_kind: lock_kind,
             size: Some(class.serial_size),
             violations: None,
             tool_tip_message: "".to_string(),
-            is_worst_3: None,
+            is_weak: None,
         });
     }
 
@@ -133,33 +183,43 @@ impl TimeTable {
         }
     }
     
-    fn update_students_num(&mut self, classes:&Vec<Class>) {
-        for i in 0..self.class_list.len() {
-            if let Some(cell) = &mut self.class_list[i] {
-                cell.student_size = Some(classes[cell.class_index].get_num_of_students());
-            }
+    
+    //連続授業の途中のコマから計算すると違反がずれるので、各授業の開始コマで計算する
+    fn update_all_violations(&mut self, room_list: &Vec<Room>, one_day_length: usize) {
+        let positions = self
+            .class_list
+            .iter()
+            .flatten()
+            .map(|x| x.get_position())
+            .collect::<Vec<CellPosition>>();
+        for position in positions {
+            self.update_violations(position.room, position.period, room_list, one_day_length);
         }
     }
-    fn update_all_process_table(&mut self, classes: &Vec<Class>) {
-        for i in 0..self.room_size {
-            for j in 0..self.period_size {
-                if let Some(class) = self.process_table[i][j].as_ref() {
-                    self.process_table[i][j] = Some(classes[class.index].clone());
+
+    //指定したコマに掛かっている授業の違反をすべて計算し直す
+    //重複はそのコマにいる授業同士、収容人数と日跨ぎは授業自身の位置だけで決まる
+    fn update_violations_in_periods(
+        &mut self,
+        periods: &BTreeSet<usize>,
+        room_list: &Vec<Room>,
+        one_day_length: usize,
+    ) {
+        let mut affected = BTreeSet::<usize>::new();
+        for &period in periods.iter().filter(|&&x| x < self.period_size) {
+            for room in 0..self.room_size {
+                if let Some(class) = self.process_table[room][period].as_ref() {
+                    affected.insert(class.index);
                 }
             }
         }
-    }
-    
-    fn update_all_violations(&mut self, room_list: &Vec<Room>, one_day_length: usize) {
-        for i in 0..self.room_size {
-            for j in 0..self.period_size {
-                if let Some(_) = self.process_table[i][j] {
-                    self.update_violations(i, j, room_list, one_day_length);
-                }
+        for class_index in affected {
+            if let Some(position) = self.class_list[class_index].as_ref().map(|x| x.get_position()) {
+                self.update_violations(position.room, position.period, room_list, one_day_length);
             }
         }
     }
-    
+
     fn update_violations(
         &mut self,
         room: usize,
@@ -184,72 +244,6 @@ impl TimeTable {
         self.class_list[class_index] = None;
     }
 
-    pub fn move_class(
-        &mut self,
-        from_room: usize,
-        from_period: usize,
-        to_room: usize,
-        to_period: usize,
-        color: Option<String>,
-        solver: &ACOSolver,
-    ) {
-        let class = self.get_class(from_room, from_period);
-        let mut pre_violations: Option<CellsViolation> = None;
-        if let Some(input_class) = &class {
-            if let Some(cell_input) = &self.class_list[input_class.index] {
-                pre_violations = cell_input.violations.clone();
-            }
-        }
-        self.remove_class(from_room, from_period);
-        self.add_class(to_room, to_period, class.clone().unwrap(), color, solver);
-        let room_list = solver.input.get_rooms();
-        self.lock(class.as_ref().unwrap().index).unwrap();
-        
-        let violations = self.get_new_violations(
-            to_room,
-            to_period,
-            room_list,
-            solver.parameters.num_of_day_lengths,
-        );
-        let class_idx = self.process_table[to_room][to_period]
-            .as_ref()
-            .unwrap()
-            .index;
-        self.class_list[class_idx].as_mut().unwrap().violations = Some(violations);
-        if let Some(violations) = pre_violations {
-            for violation in violations.same_student_same_time {
-                let period = violation.period;
-                for room in violation.rooms {
-                    self.update_violations(
-                        room,
-                        period,
-                        room_list,
-                        solver.parameters.num_of_day_lengths,
-                    );
-                }
-            }
-        }
-        let mut post_violations: Option<CellsViolation> = None;
-        if let Some(input_class) = &class {
-            if let Some(cell_input) = &self.class_list[input_class.index] {
-                post_violations = cell_input.violations.clone();
-            }
-        }
-        if let Some(violations) = post_violations {
-            for violation in violations.same_student_same_time {
-                let period = violation.period;
-                for room in violation.rooms {
-                    self.update_violations(
-                        room,
-                        period,
-                        room_list,
-                        solver.parameters.num_of_day_lengths,
-                    );
-                }
-            }
-        }
-    }
-
     pub fn calc_same_student_same_time(&self, room_id: usize, period_id: usize) -> Vec<Violations> {
         let mut violations = Vec::<Violations>::new();
         let serial_size = self.process_table[room_id][period_id]
@@ -266,7 +260,7 @@ impl TimeTable {
             .unwrap()
             .index;
         //println!("{},{},{}",period_id,serial_size,self.process_table[room_id][period_id].as_ref().unwrap().name);
-        for time in period_id..min(period_id + serial_size,25) {
+        for time in period_id..min(period_id + serial_size, self.period_size) {
             for room in 0..self.room_size {
                 if time == period_id && room == room_id {
                     continue;
@@ -314,7 +308,7 @@ impl TimeTable {
             .as_ref()
             .unwrap()
             .index;
-        for time in period_id..min(25,period_id + serial_size) {
+        for time in period_id..min(self.period_size, period_id + serial_size) {
             for room in 0..self.room_size {
                 if time == period_id && room == room_id {
                     continue;
@@ -413,18 +407,22 @@ impl TimeTable {
             .iter()
             .map(|x| x.as_ref().unwrap().is_locked.unwrap_or(false))
             .collect::<Vec<bool>>();
+        let lock_kind_list = self
+            .class_list
+            .iter()
+            .map(|x| x.as_ref().unwrap().lock_kind)
+            .collect::<Vec<Option<LockKind>>>();
         self.class_list = Vec::<Option<ActiveCell>>::new();
         let teachers = solver.input.get_teachers();
         for i in 0..self.room_size {
             for j in 0..self.period_size {
                 if let Some(class) = self.process_table[i][j].as_ref() {
-                    let id = i * self.period_size + j;
                     self.class_list[class.index] = Some(ActiveCell {
-                        id: id + self.period_size * self.room_size,
+                        id: class.id,
                         period: j,
                         room: i,
                         class_index: class.index,
-                        class_name: format!("{},{},{}", id, class.index, class.name),
+                        class_name: format!("{},{},{}", class.id, class.index, class.name),
                         teachers: Some(
                             class
                                 .teacher_indexes
@@ -436,10 +434,11 @@ impl TimeTable {
                         student_size: Some(class.num_of_students),
                         color: None, //ここsolverから取得する
                         is_locked: Some(is_locked_list[class.index]),
+                        lock_kind: lock_kind_list[class.index],
                         size: Some(class.serial_size),
                         violations: None,
                         tool_tip_message: "".to_string(),
-                        is_worst_3: None,
+                        is_weak: None,
                     });
                 } else {
                     self.class_list.push(None);
@@ -448,64 +447,61 @@ impl TimeTable {
         }
     }
     
-    pub fn update_worst3_cell(&mut self, solver: &ACOSolver) {
-        //calc_prob_from_v_igunore_visitedが下から3番目までのclass_listのworst3をtrueにする
-        let mut prob_tuple = Vec::<(f64, usize)>::new();
-        for i in 0..self.class_list.len() {
-            if let Some(cell) = &self.class_list[i] {
-                if let Some(ant) = solver.get_best_ant() {
-                    let (rp_v, prov_v) =
-                        ant.calc_prob_from_v_igunore_visited(cell.class_index, solver.colony.get_graph());
-                    let mut prov = 0.0;
-                    for (i, rp) in rp_v.iter().enumerate() {
-                        if rp[0] == cell.room && rp[1] == cell.period {
-                            prov = prov_v[i];
-                        }
-                    }
-                    prob_tuple.push((prov, i));
-                }
-            }
-        }
-        prob_tuple.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-        for i in self.class_list.iter_mut() {
-            if let Some(cell) = i {
-                cell.is_worst_3 = Some(false);
-            }
-        }
-        //3番目までをtrueにする
-        for i in 0..3 {
-            if let Some(cell) = &mut self.class_list[prob_tuple[i].1] {
-                cell.is_worst_3 = Some(true);
+    //弱い配置の授業に印を付ける
+    pub fn update_weak_cells(
+        &mut self,
+        solver: &ACOSolver,
+        config: &WeakPlacementConfig,
+    ) -> Vec<WeakPlacement> {
+        let placements = analyze_weak_placements(self, solver, config);
+        for placement in placements.iter() {
+            if let Some(cell) = self.class_list[placement.class_index].as_mut() {
+                cell.is_weak = Some(placement.is_weak);
             }
         }
+        placements
     }
 
-    pub fn lock(&mut self, class_index: usize) -> Result<(), String> {
-        self.class_list[class_index].as_mut().unwrap().is_locked = Some(true);
-        self.class_list[class_index].as_mut().unwrap().color = Some(
-            "#AAAAFF".to_string(),
-        );
+    pub fn lock(&mut self, class_index: usize, kind: LockKind) -> Result<(), CommandError> {
+        let cell = self
+            .class_list
+            .get_mut(class_index)
+            .and_then(|x| x.as_mut())
+            .ok_or(CommandError::ClassNotFound(class_index))?;
+        cell.is_locked = Some(true);
+        cell.lock_kind = Some(kind);
+        cell.color = Some(get_lock_color(kind));
         return Ok(());
     }
-    pub fn unlock(&mut self, class_index: usize,solver: &ACOSolver) -> Result<(), String> {
-        self.class_list[class_index].as_mut().unwrap().is_locked = Some(false);
-        self.class_list[class_index].as_mut().unwrap().color = Some(
-            calc_color_from_cell(
-                &solver,
-                self.class_list[class_index].as_ref().unwrap(),
-            ),
-        );
+    pub fn unlock(&mut self, class_index: usize,solver: &ACOSolver) -> Result<(), CommandError> {
+        let cell = self
+            .class_list
+            .get_mut(class_index)
+            .and_then(|x| x.as_mut())
+            .ok_or(CommandError::ClassNotFound(class_index))?;
+        cell.is_locked = Some(false);
+        cell.lock_kind = None;
+        cell.color = Some(calc_color_from_cell(&solver, cell));
         return Ok(());
     }
 }
 
+//固定の仕方ごとにセルの色を変える
+pub fn get_lock_color(kind: LockKind) -> String {
+    match kind {
+        LockKind::Both => "#AAAAFF".to_string(),
+        LockKind::PeriodOnly => "#AADDFF".to_string(),
+        LockKind::RoomOnly => "#DDAAFF".to_string(),
+    }
+}
+
 pub fn convert_solver_to_timetable(solver: &ACOSolver) -> Result<TimeTable, Box<dyn Error>> {
     let mut time_table = TimeTable::new(
         solver.parameters.num_of_rooms,
         solver.parameters.num_of_periods,
         solver.input.get_classes().len(),
     );
-    let best_ant = solver.get_best_ant().ok_or("No best ant found")?;
+    let best_ant = solver.get_best_ant().ok_or(CommandError::NoSolution)?;
     let classes = solver.input.get_classes();
     for (class_id, &[room_id, period_id]) in best_ant.get_corresponding_crp().iter().enumerate() {
         let class = classes[class_id].clone();
@@ -532,28 +528,9 @@ pub fn convert_solver_to_timetable(solver: &ACOSolver) -> Result<TimeTable, Box<
                 .violations = violations;
         }
     }
-    time_table.update_worst3_cell(solver);
     Ok(time_table)
 }
 
-pub struct TimeTableManager {
-    pub timetable_manager: Mutex<Option<TimeTable>>,
-}
-
-pub fn save_timetable(timetable_manager: tauri::State<'_, TimeTableManager>, timetable: TimeTable) {
-    let mut managed_timetable = timetable_manager.timetable_manager.lock().unwrap();
-    *managed_timetable = Some(timetable);
-}
-
-pub fn save_solver(
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-    solver: ACOSolver,
-) -> Result<(), Box<dyn Error>> {
-    let mut managed_solver = solver_manager.solver.lock().unwrap();
-    *managed_solver = Some(solver);
-    Ok(())
-}
-
 fn calc_color_init(
     solver: &ACOSolver,
     class_id: usize,
@@ -562,9 +539,9 @@ fn calc_color_init(
 ) -> String {
     let mut res = get_pheromone_color(solver, class_id, room_id, period_id);
 
-    if let Some(is_lock) = solver.colony.get_graph().get_classes_is_locked(class_id) {
-        if is_lock.0 == room_id as usize && is_lock.1 == period_id as usize {
-            res = "#AAAAFF".to_string();
+    if let Some(lock) = solver.colony.get_graph().get_classes_is_locked(class_id) {
+        if lock.allows(room_id, period_id) {
+            res = get_lock_color(lock.kind);
         }
     }
     return res;
@@ -611,8 +588,8 @@ fn get_pheromone_color(
 }
 
 fn calc_color_from_cell(solver: &ACOSolver, active_cell: &ActiveCell) -> String {
-    if active_cell.is_locked.unwrap_or(false) {
-        return "#AAAAFF".to_string();
+    if let Some(lock) = active_cell.get_lock() {
+        return get_lock_color(lock.kind);
     }
     let class_id = active_cell.class_index;
     let room_id = active_cell.room;
@@ -622,258 +599,223 @@ fn calc_color_from_cell(solver: &ACOSolver, active_cell: &ActiveCell) -> String
 
 #[tauri::command]
 pub fn is_swappable(
-    time_table_manager: tauri::State<'_, TimeTableManager>,
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-    over_id: usize,
-    active_id: usize,
-) -> Result<bool, String> {
-    info!("called is_swappable,{},{}", over_id, active_id);
-    let managed_solver = solver_manager.solver.lock().unwrap();
-    let parameter = managed_solver
-        .as_ref()
-        .ok_or("No solver found")?
-        .parameters
-        .clone();
-
-    let periods_size = parameter.num_of_periods;
-    let rooms_size = parameter.num_of_rooms;
-    let mut is_swappable = true;
-    let over_room = over_id / periods_size;
-    let over_period = over_id % periods_size;
-    if let Some(time_table) = time_table_manager
-        .timetable_manager
-        .lock()
-        .unwrap()
-        .as_ref()
-    {
-        info!(
-            "active_id:{},room:{},period:{}",
-            active_id - periods_size * rooms_size,
-            (active_id - rooms_size * periods_size) / periods_size,
-            active_id % periods_size
-        );
-        let active_size = time_table.process_table
-            [(active_id - rooms_size * periods_size) / periods_size][active_id % periods_size]
-            .as_ref()
-            .unwrap()
-            .serial_size;
-        //no over the day
-        let active_index = time_table.process_table
-            [(active_id - rooms_size * periods_size) / periods_size][active_id % periods_size]
-            .as_ref()
-            .unwrap()
-            .index;
-        for day in over_period..(over_period + active_size) {
-            if day >= periods_size {
-                is_swappable = false;
-                break;
-            }
-            if let Some(class) = time_table.process_table[over_room][day].as_ref() {
-                if class.index != active_index {
-                    is_swappable = false;
-                    break;
-                }
-            }
-        }
-    }
-    return Ok(is_swappable);
+    session_manager: tauri::State<'_, SessionManager>,
+    class_id: usize,
+    to: CellPosition,
+) -> Result<bool, CommandError> {
+    info!("called is_swappable,{},{:?}", class_id, to);
+    let session = session_manager.session.lock_state()?;
+    let (time_table, solver) = session.get_timetable_and_solver()?;
+    let class_index = time_table
+        .get_class_index_by_id(class_id)
+        .ok_or(CommandError::ClassNotFound(class_id))?;
+    return Ok(
+        plan_move(time_table, class_index, to, solver.parameters.num_of_day_lengths).is_some(),
+    );
 }
 
-//Assume all swap destinations are blankcells
+//移動先が埋まっているときは入れ替えるか、邪魔な授業を近くの空きコマへ押し出す
 #[tauri::command]
 pub fn handle_swap_cell(
-    timetable_manager: tauri::State<'_, TimeTableManager>,
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-    over_id: usize,
-    active_id: usize,
-) -> Result<TimeTable, String> {
-    info!("called handle_swap_cell,{},{}", over_id, active_id);
-    let mut managed_timetable = timetable_manager.timetable_manager.lock().unwrap();
-    let solver = solver_manager.solver.lock().unwrap();
-    if let Some(time_table) = managed_timetable.as_mut() {
-        let room_size = time_table.room_size;
-        let period_size = time_table.period_size;
-        let new_id = active_id - period_size * room_size;
-        let active_period = new_id % period_size;
-        let active_room = new_id / period_size;
-        let over_period = over_id % period_size;
-        let over_room = over_id / period_size;
-        let index = time_table.process_table[active_room][active_period]
-            .as_ref()
-            .unwrap()
-            .index;
-        let mut color =
-            get_pheromone_color(solver.as_ref().unwrap(), index, over_room, over_period);
-        let is_locked = time_table.class_list[index]
-            .as_ref()
-            .unwrap()
-            .is_locked
-            .unwrap_or(false);
-        if is_locked {
-            color = "#AAAAFF".to_string();
-        }
-        time_table.move_class(
-            active_room,
-            active_period,
-            over_room,
-            over_period,
-            Some(color),
-            solver.as_ref().unwrap(),
-        );
-        time_table.update_worst3_cell(solver.as_ref().unwrap());
-        return Ok(time_table.clone());
-    }
-    return Err("No timetable found".to_string());
+    session_manager: tauri::State<'_, SessionManager>,
+    class_id: usize,
+    to: CellPosition,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
+    info!("called handle_swap_cell,{},{:?}", class_id, to);
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let (time_table, solver) = session.get_editable()?;
+    let index = time_table
+        .get_class_index_by_id(class_id)
+        .ok_or(CommandError::ClassNotFound(class_id))?;
+    let day_length = solver.parameters.num_of_day_lengths;
+    let moves = plan_move(time_table, index, to, day_length).ok_or(
+        CommandError::InvalidOperation(format!(
+            "class {} cannot be moved to {:?}",
+            class_id, to
+        )),
+    )?;
+    let before = time_table.clone();
+    //コマだけ・部屋だけのロックはそのまま、ロックしていなかった授業は両方固定する
+    let lock_kind = before.class_list[index]
+        .as_ref()
+        .and_then(|cell| cell.get_lock())
+        .map(|lock| lock.kind)
+        .unwrap_or(LockKind::Both);
+    apply_moves(time_table, &moves, solver);
+    //手で動かした授業はロックする
+    time_table.lock(index, lock_kind)?;
+    session.annotate()?;
+    session.record_edit(EditKind::SwapCell, before)?;
+    return session.clone_timetable();
+}
+
+//保存されている違反と計算し直した違反が食い違っていた授業
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ViolationRecompute {
+    pub stale_class_ids: Vec<usize>,
+    pub timetable: TimeTable,
 }
 
+//全セルの違反を計算し直し、差分があった授業を返す
 #[tauri::command]
-pub fn handle_switch_lock(
-    timetable_manager: tauri::State<'_, TimeTableManager>,
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-    id: usize,
-) -> Result<TimeTable, String> {
-    info!("called handle_switch_lock,{}", id);
-    let mut managed_timetable = timetable_manager.timetable_manager.lock().unwrap();
-    let solver = solver_manager.solver.lock().unwrap();
-    if let Some(time_table) = managed_timetable.as_mut() {
-        let room = (id - time_table.room_size * time_table.period_size) / time_table.period_size;
-        let period = (id - time_table.room_size * time_table.period_size) % time_table.period_size;
-        let class_index = time_table.process_table[room][period]
-            .as_ref()
-            .unwrap()
-            .index;
+pub fn handle_recompute_violations(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<ViolationRecompute, CommandError> {
+    info!("called handle_recompute_violations");
+    let mut session = session_manager.session.lock_state()?;
+    let (time_table, solver) = session.get_editable()?;
+    let before = time_table.class_list.clone();
+    time_table.update_all_violations(solver.input.get_rooms(), solver.parameters.num_of_day_lengths);
+    session.annotate()?;
+    let stale_class_ids = before
+        .iter()
+        .zip(session.get_timetable()?.class_list.iter())
+        .filter_map(|(before, after)| match (before, after) {
+            (Some(before), Some(after)) if before.violations != after.violations => Some(after.id),
+            _ => None,
+        })
+        .collect::<Vec<usize>>();
+    if !stale_class_ids.is_empty() {
+        warn!("stale violations: {:?}", stale_class_ids);
+        session.touch();
+    }
+    return Ok(ViolationRecompute {
+        stale_class_ids,
+        timetable: session.clone_timetable()?,
+    });
+}
 
-        time_table.class_list[class_index]
-            .as_mut()
-            .unwrap()
-            .is_locked = Some(
-            !time_table.class_list[class_index]
-                .as_ref()
-                .unwrap()
-                .is_locked
-                .unwrap_or(false),
-        );
-        time_table.class_list[class_index].as_mut().unwrap().color = Some(calc_color_from_cell(
-            solver.as_ref().unwrap(),
-            time_table.class_list[class_index].as_ref().unwrap(),
-        ));
-        return Ok(time_table.clone());
+#[tauri::command]
+pub fn handle_switch_lock(
+    session_manager: tauri::State<'_, SessionManager>,
+    class_id: usize,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
+    info!("called handle_switch_lock,{}", class_id);
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let (time_table, solver) = session.get_editable()?;
+    let class_index = time_table
+        .get_class_index_by_id(class_id)
+        .ok_or(CommandError::ClassNotFound(class_id))?;
+    let before = time_table.clone();
+    if time_table.class_list[class_index]
+        .as_ref()
+        .ok_or(CommandError::ClassNotFound(class_id))?
+        .is_locked
+        .unwrap_or(false)
+    {
+        time_table.unlock(class_index, solver)?;
+    } else {
+        time_table.lock(class_index, LockKind::Both)?;
     }
-    return Err("No timetable found".to_string());
+    session.record_edit(EditKind::SwitchLock, before)?;
+    return session.clone_timetable();
+}
+
+
+//kindがNoneなら固定を外す
+#[tauri::command]
+pub fn handle_set_lock(
+    session_manager: tauri::State<'_, SessionManager>,
+    class_id: usize,
+    kind: Option<LockKind>,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
+    info!("called handle_set_lock,{},{:?}", class_id, kind);
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let (time_table, solver) = session.get_editable()?;
+    let class_index = time_table
+        .get_class_index_by_id(class_id)
+        .ok_or(CommandError::ClassNotFound(class_id))?;
+    let before = time_table.clone();
+    match kind {
+        Some(kind) => time_table.lock(class_index, kind)?,
+        None => time_table.unlock(class_index, solver)?,
+    }
+    session.record_edit(EditKind::SetLock, before)?;
+    return session.clone_timetable();
 }
 
 
 #[tauri::command]
 pub fn handle_lock_no_violation(
-    timetable_manager: tauri::State<'_, TimeTableManager>,
-) -> Result<TimeTable, String> {
+    session_manager: tauri::State<'_, SessionManager>,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
     info!("called handle_rock_allof_violation");
-    let mut managed_timetable = timetable_manager.timetable_manager.lock().unwrap();
-    if let Some(time_table) = managed_timetable.as_mut() {
-        for i in 0..time_table.class_list.len() {
-            if let Some(cell) = &time_table.class_list[i] {
-                if !cell.violations.as_ref().unwrap().is_violated {
-                    time_table.lock(i).unwrap();
-                }
-            }
-        }
-        return Ok(time_table.clone());
-    }
-    return Err("No timetable found".to_string());
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let (time_table, _) = session.get_editable()?;
+    let before = time_table.clone();
+    for i in 0..time_table.class_list.len() {
+        let is_violated = match &time_table.class_list[i] {
+            Some(cell) => cell.violations.as_ref().map_or(false, |x| x.is_violated),
+            None => continue,
+        };
+        if !is_violated {
+            time_table.lock(i, LockKind::Both)?;
+        }
+    }
+    session.record_edit(EditKind::LockNoViolation, before)?;
+    return session.clone_timetable();
 }
 
 #[tauri::command]
 pub fn handle_unlock_violation(
-    timetable_manager: tauri::State<'_, TimeTableManager>,
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-) -> Result<TimeTable, String> {
+    session_manager: tauri::State<'_, SessionManager>,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
     info!("called handle_unlock_violation");
-    let mut managed_timetable = timetable_manager.timetable_manager.lock().unwrap();
-    let solver = solver_manager.solver.lock().unwrap();
-    if let Some(time_table) = managed_timetable.as_mut() {
-        for i in 0..time_table.class_list.len() {
-            if let Some(cell) = &time_table.class_list[i] {
-                if cell.violations.as_ref().unwrap().is_violated {
-                    time_table.unlock(i, solver.as_ref().unwrap()).unwrap();
-                }
-            }
-        }
-        return Ok(time_table.clone());
-    }
-    return Err("No timetable found".to_string());
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let (time_table, solver) = session.get_editable()?;
+    let before = time_table.clone();
+    for i in 0..time_table.class_list.len() {
+        let is_violated = match &time_table.class_list[i] {
+            Some(cell) => cell.violations.as_ref().map_or(false, |x| x.is_violated),
+            None => continue,
+        };
+        if is_violated {
+            time_table.unlock(i, solver)?;
+        }
+    }
+    session.record_edit(EditKind::UnlockViolation, before)?;
+    return session.clone_timetable();
 }
 
+#[tauri::command]
+pub fn handle_undo(
+    session_manager: tauri::State<'_, SessionManager>,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
+    info!("called handle_undo");
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    session.undo()?;
+    return session.clone_timetable();
+}
 
-const DUMP_PATH: &str = "ClassistAnt";
-const DUMP_TIMETABLE_FILE: &str = "timetable.json";
-const DUMP_SOLVER_FILE: &str = "solver.json";
-use tauri::api::path::config_dir;
 #[tauri::command]
-pub fn dump_timetable(
-    timetable_manager: tauri::State<'_, TimeTableManager>,
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-) {
-    info!("called dump_timetable");
-    let json = serde_json::to_string(
-        timetable_manager
-            .timetable_manager
-            .lock()
-            .unwrap()
-            .as_ref()
-            .unwrap(),
-    )
-    .unwrap();
-    if let Some(mut path) = config_dir() {
-        path.push(DUMP_PATH);
-        std::fs::create_dir_all(&path).unwrap();
-        path.push(DUMP_TIMETABLE_FILE);
-        let mut file = File::create(path).unwrap();
-        file.write_all(json.as_bytes()).unwrap();
-    }
-    let solver_json =
-        serde_json::to_string(solver_manager.solver.lock().unwrap().as_ref().unwrap()).unwrap();
-    if let Some(mut path) = config_dir() {
-        path.push(DUMP_PATH);
-        std::fs::create_dir_all(&path).unwrap();
-        path.push(DUMP_SOLVER_FILE);
-        let mut file = File::create(path).unwrap();
-        file.write_all(solver_json.as_bytes()).unwrap();
-    }
+pub fn handle_redo(
+    session_manager: tauri::State<'_, SessionManager>,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
+    info!("called handle_redo");
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    session.redo()?;
+    return session.clone_timetable();
 }
 
 #[tauri::command]
-pub fn load_timetable(
-    timetable_manager: tauri::State<'_, TimeTableManager>,
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-) -> Result<TimeTable, String> {
-    let input = input::Input::new();
-    let mut parameters :Option<aco::aco_parameters::AcoParameters> = None;
-    if let Some(mut path) = config_dir() {
-        path.push(DUMP_PATH);
-        path.push(DUMP_SOLVER_FILE);
-        let mut file = File::open(path).unwrap();
-        let mut json = String::new();
-        file.read_to_string(&mut json).unwrap();
-        let mut solver: ACOSolver = serde_json::from_str(&json).unwrap();
-        solver.input = input.clone();
-        parameters = Some(solver.parameters.clone());
-        save_solver(solver_manager, solver.clone()).unwrap();
-    }
-    if let Some(mut path) = config_dir() {
-        path.push(DUMP_PATH);
-        std::fs::create_dir_all(&path).unwrap();
-        path.push(DUMP_TIMETABLE_FILE);
-        let mut file = File::open(path).unwrap();
-        let mut json = String::new();
-        file.read_to_string(&mut json).unwrap();
-        let mut timetable: TimeTable = serde_json::from_str(&json).unwrap();
-        timetable.update_students_num(input.get_classes());
-        timetable.update_all_process_table(input.get_classes());
-        if let Some(parameters) = parameters {
-            timetable.update_all_violations(input.get_rooms(), parameters.num_of_day_lengths);
-        }
-        save_timetable(timetable_manager, timetable.clone());
-        return Ok(timetable);
-    }
-    return Err("No timetable found".to_string());
+pub fn handle_get_history(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<HistoryState, CommandError> {
+    info!("called handle_get_history");
+    return Ok(session_manager.session.lock_state()?.history.get_state());
 }
@@ -0,0 +1,152 @@
+// 教員・学生グループ・部屋・曜日・コマの範囲でまとめてロックする
+use super::cell::ActiveCell;
+use super::history::EditKind;
+use super::TimeTable;
+use crate::algorithm::aco::aco_solver::ACOSolver;
+use crate::algorithm::aco::graph::LockKind;
+use crate::input::Input;
+use log::info;
+use serde::{Deserialize, Serialize};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+//indexはinputの教員・学生グループ・部屋のindex
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum LockSelector {
+    Teacher { index: usize },
+    StudentGroup { index: usize },
+    Room { index: usize },
+    Day { day: usize },
+    //fromからtoまでのコマ (toを含む)、連続授業は一部でも掛かれば対象
+    Periods { from: usize, to: usize },
+}
+
+impl LockSelector {
+    pub fn matches(&self, cell: &ActiveCell, input: &Input, day_length: usize) -> bool {
+        let class = match input.get_classes().get(cell.class_index) {
+            Some(class) => class,
+            None => return false,
+        };
+        let overlaps = |from: usize, to: usize| {
+            let end = cell.period + cell.size.unwrap_or(1) - 1;
+            cell.period <= to && from <= end
+        };
+        match self {
+            LockSelector::Teacher { index } => class.teacher_indexes.contains(index),
+            LockSelector::StudentGroup { index } => class.students_group_indexes.contains(index),
+            LockSelector::Room { index } => cell.room == *index,
+            LockSelector::Day { day } => overlaps(day * day_length, (day + 1) * day_length - 1),
+            LockSelector::Periods { from, to } => overlaps(*from, *to),
+        }
+    }
+}
+
+//どれか1つのselectorに当てはまる授業のindex
+pub fn select_classes(
+    timetable: &TimeTable,
+    selectors: &[LockSelector],
+    input: &Input,
+    day_length: usize,
+) -> Vec<usize> {
+    timetable
+        .class_list
+        .iter()
+        .flatten()
+        .filter(|cell| selectors.iter().any(|x| x.matches(cell, input, day_length)))
+        .map(|cell| cell.class_index)
+        .collect()
+}
+
+fn edit_locks(
+    session_manager: tauri::State<'_, SessionManager>,
+    revision: Option<u64>,
+    edit_kind: EditKind,
+    edit: impl FnOnce(&mut TimeTable, &ACOSolver) -> Result<(), CommandError>,
+) -> Result<TimeTable, CommandError> {
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let (time_table, solver) = session.get_editable()?;
+    let before = time_table.clone();
+    edit(time_table, solver)?;
+    session.record_edit(edit_kind, before)?;
+    session.clone_timetable()
+}
+
+//kindを省略すると部屋とコマの両方を固定する
+#[tauri::command]
+pub fn handle_lock_selection(
+    session_manager: tauri::State<'_, SessionManager>,
+    selectors: Vec<LockSelector>,
+    kind: Option<LockKind>,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
+    info!("called handle_lock_selection {:?} {:?}", selectors, kind);
+    edit_locks(
+        session_manager,
+        revision,
+        EditKind::LockSelection,
+        |time_table, solver| {
+            let day_length = solver.parameters.num_of_day_lengths;
+            for class_index in select_classes(time_table, &selectors, &solver.input, day_length) {
+                time_table.lock(class_index, kind.unwrap_or(LockKind::Both))?;
+            }
+            Ok(())
+        },
+    )
+}
+
+#[tauri::command]
+pub fn handle_unlock_selection(
+    session_manager: tauri::State<'_, SessionManager>,
+    selectors: Vec<LockSelector>,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
+    info!("called handle_unlock_selection {:?}", selectors);
+    edit_locks(
+        session_manager,
+        revision,
+        EditKind::UnlockSelection,
+        |time_table, solver| {
+            let day_length = solver.parameters.num_of_day_lengths;
+            for class_index in select_classes(time_table, &selectors, &solver.input, day_length) {
+                time_table.unlock(class_index, solver)?;
+            }
+            Ok(())
+        },
+    )
+}
+
+//選んだ授業だけロックを外し、それ以外はすべてロックする
+#[tauri::command]
+pub fn handle_lock_all_except(
+    session_manager: tauri::State<'_, SessionManager>,
+    selectors: Vec<LockSelector>,
+    kind: Option<LockKind>,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
+    info!("called handle_lock_all_except {:?} {:?}", selectors, kind);
+    edit_locks(
+        session_manager,
+        revision,
+        EditKind::LockAllExcept,
+        |time_table, solver| {
+            let day_length = solver.parameters.num_of_day_lengths;
+            let selected = select_classes(time_table, &selectors, &solver.input, day_length);
+            let placed = time_table
+                .class_list
+                .iter()
+                .flatten()
+                .map(|x| x.class_index)
+                .collect::<Vec<usize>>();
+            for class_index in placed {
+                if selected.contains(&class_index) {
+                    time_table.unlock(class_index, solver)?;
+                } else {
+                    time_table.lock(class_index, kind.unwrap_or(LockKind::Both))?;
+                }
+            }
+            Ok(())
+        },
+    )
+}
@@ -1,3 +1,4 @@
+use super::super::aco::graph::{ClassLock, LockKind};
 use super::super::aco::violations::CellsViolation;
 use serde::{Deserialize, Serialize};
 use std::convert::AsMut;
@@ -23,9 +24,18 @@ impl AsMut<Cell> for Cell {
     }
 }
 
+//部屋とコマの組、セルのidとは独立している
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CellPosition {
+    pub room: usize,
+    pub period: usize,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ActiveCell {
+    //inputのclass id、部屋やコマを変えても変わらない
     pub id: usize,
     pub period: usize,
     pub room: usize,
@@ -36,10 +46,35 @@ pub struct ActiveCell {
     pub student_size: Option<usize>,
     pub color: Option<String>,
     pub is_locked: Option<bool>,
+    //is_lockedのときの固定の仕方、Noneは部屋とコマの両方
+    #[serde(default)]
+    pub lock_kind: Option<LockKind>,
     pub size: Option<usize>,
     pub violations: Option<CellsViolation>,
     pub tool_tip_message: String,
-    pub is_worst_3: Option<bool>,
+    //以前はworst 3の印だった
+    #[serde(alias = "isWorst3")]
+    pub is_weak: Option<bool>,
+}
+
+impl ActiveCell {
+    pub fn get_lock(&self) -> Option<ClassLock> {
+        if !self.is_locked.unwrap_or(false) {
+            return None;
+        }
+        Some(ClassLock {
+            kind: self.lock_kind.unwrap_or(LockKind::Both),
+            room: self.room,
+            period: self.period,
+        })
+    }
+
+    pub fn get_position(&self) -> CellPosition {
+        CellPosition {
+            room: self.room,
+            period: self.period,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -0,0 +1,217 @@
+// セルのtool_tip_messageに出す違反の説明
+use super::cell::ActiveCell;
+use super::TimeTable;
+use crate::algorithm::aco::aco_parameters::get_day_label;
+use crate::algorithm::aco::violations::Violations;
+use crate::input::Input;
+use log::info;
+use serde::{Deserialize, Serialize};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Language {
+    #[default]
+    Ja,
+    En,
+}
+
+static DAYS_OF_WEEK_EN: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+fn get_ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (1, x) if x != 11 => "st",
+        (2, x) if x != 12 => "nd",
+        (3, x) if x != 13 => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+fn get_day_name(day: usize, language: Language) -> String {
+    match language {
+        Language::Ja => get_day_label(day),
+        Language::En => DAYS_OF_WEEK_EN[day % DAYS_OF_WEEK_EN.len()].to_string(),
+    }
+}
+
+//火曜日 2限 / Tue 2nd
+fn get_period_name(period: usize, day_length: usize, language: Language) -> String {
+    let day = get_day_name(period / day_length, language);
+    match language {
+        Language::Ja => format!("{} {}限", day, period % day_length + 1),
+        Language::En => format!("{} {}", day, get_ordinal(period % day_length + 1)),
+    }
+}
+
+//同じコマにいる相手の授業と、共通する教員または学生グループ
+struct Conflict {
+    member_name: String,
+    class_name: String,
+    room_name: String,
+    period: usize,
+}
+
+fn get_conflicts(
+    timetable: &TimeTable,
+    input: &Input,
+    violations: &[Violations],
+    get_members: impl Fn(usize) -> Vec<usize>,
+    own_members: &[usize],
+    get_name: impl Fn(usize) -> String,
+) -> Vec<Conflict> {
+    let mut res = Vec::new();
+    for violation in violations.iter() {
+        for &room in violation.rooms.iter() {
+            if let Some(class) = &timetable.process_table[room][violation.period] {
+                let room_name = input
+                    .get_rooms()
+                    .get(room)
+                    .map(|x| x.name.clone())
+                    .unwrap_or_default();
+                for member in get_members(class.index) {
+                    if own_members.contains(&member) {
+                        res.push(Conflict {
+                            member_name: get_name(member),
+                            class_name: class.name.clone(),
+                            room_name: room_name.clone(),
+                            period: violation.period,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    res
+}
+
+pub fn explain_violations(
+    timetable: &TimeTable,
+    cell: &ActiveCell,
+    input: &Input,
+    day_length: usize,
+    language: Language,
+) -> String {
+    let violations = match &cell.violations {
+        Some(violations) if violations.is_violated => violations,
+        _ => return String::new(),
+    };
+    let class = match input.get_classes().get(cell.class_index) {
+        Some(class) => class,
+        None => return String::new(),
+    };
+    let classes = input.get_classes();
+    let mut lines = Vec::new();
+
+    for conflict in get_conflicts(
+        timetable,
+        input,
+        &violations.same_student_same_time,
+        |i| classes[i].students_group_indexes.clone(),
+        &class.students_group_indexes,
+        |i| input.get_student_groups()[i].name.clone(),
+    ) {
+        let period = get_period_name(conflict.period, day_length, language);
+        lines.push(match language {
+            Language::Ja => format!(
+                "{}は{}に{}で{}も受講しています",
+                conflict.member_name, period, conflict.room_name, conflict.class_name
+            ),
+            Language::En => format!(
+                "Group {} also attends {} in {} at {}",
+                conflict.member_name, conflict.class_name, conflict.room_name, period
+            ),
+        });
+    }
+    for conflict in get_conflicts(
+        timetable,
+        input,
+        &violations.same_teacher_same_time,
+        |i| classes[i].teacher_indexes.clone(),
+        &class.teacher_indexes,
+        |i| input.get_teachers()[i].name.clone(),
+    ) {
+        let period = get_period_name(conflict.period, day_length, language);
+        lines.push(match language {
+            Language::Ja => format!(
+                "{}先生は{}に{}で{}も担当しています",
+                conflict.member_name, period, conflict.room_name, conflict.class_name
+            ),
+            Language::En => format!(
+                "Teacher {} also teaches {} in {} at {}",
+                conflict.member_name, conflict.class_name, conflict.room_name, period
+            ),
+        });
+    }
+    if !violations.capacity_over.is_empty() {
+        let room = &input.get_rooms()[cell.room];
+        lines.push(match language {
+            Language::Ja => format!(
+                "{}の定員{}人 < 受講者{}人",
+                room.name, room.capacity, class.num_of_students
+            ),
+            Language::En => format!(
+                "Room capacity {} < {} students",
+                room.capacity, class.num_of_students
+            ),
+        });
+    }
+    if !violations.strabble_days.is_empty() {
+        let day = get_day_name(cell.period / day_length, language);
+        lines.push(match language {
+            Language::Ja => format!(
+                "{}コマ連続の授業が{}の最後をはみ出しています",
+                class.serial_size, day
+            ),
+            Language::En => format!(
+                "{}-period class runs past the end of {}",
+                class.serial_size, day
+            ),
+        });
+    }
+    lines.join("\n")
+}
+
+impl TimeTable {
+    pub fn update_tool_tip_messages(&mut self, input: &Input, day_length: usize, language: Language) {
+        let messages = self
+            .class_list
+            .iter()
+            .map(|x| {
+                x.as_ref()
+                    .map(|cell| explain_violations(self, cell, input, day_length, language))
+            })
+            .collect::<Vec<Option<String>>>();
+        for (cell, message) in self.class_list.iter_mut().zip(messages.into_iter()) {
+            if let (Some(cell), Some(message)) = (cell.as_mut(), message) {
+                cell.tool_tip_message = message;
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn handle_get_language(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<Language, CommandError> {
+    info!("called handle_get_language");
+    Ok(session_manager.session.lock_state()?.language)
+}
+
+//言語を変えたら今の時間割の説明も作り直す
+#[tauri::command]
+pub fn handle_set_language(
+    session_manager: tauri::State<'_, SessionManager>,
+    language: Language,
+) -> Result<Option<TimeTable>, CommandError> {
+    info!("called handle_set_language {:?}", language);
+    let mut session = session_manager.session.lock_state()?;
+    session.language = language;
+    if session.get_timetable().is_err() {
+        return Ok(None);
+    }
+    session.annotate()?;
+    session.touch();
+    Ok(Some(session.clone_timetable()?))
+}
@@ -0,0 +1,80 @@
+use super::TimeTable;
+use serde::{Deserialize, Serialize};
+
+const MAX_HISTORY_SIZE: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum EditKind {
+    SwapCell,
+    SwitchLock,
+    SetLock,
+    LockNoViolation,
+    UnlockViolation,
+    LockSelection,
+    UnlockSelection,
+    LockAllExcept,
+    RestoreSnapshot,
+}
+
+//複数セルを変更する操作も1ステップとして前後の時間割を持つ
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    kind: EditKind,
+    before: TimeTable,
+    after: TimeTable,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryState {
+    pub undo: Vec<EditKind>,
+    pub redo: Vec<EditKind>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EditHistory {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+}
+
+impl EditHistory {
+    pub fn record(&mut self, kind: EditKind, before: TimeTable, after: TimeTable) {
+        self.undo_stack.push(HistoryEntry {
+            kind,
+            before,
+            after,
+        });
+        if self.undo_stack.len() > MAX_HISTORY_SIZE {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    pub fn undo(&mut self) -> Option<TimeTable> {
+        let entry = self.undo_stack.pop()?;
+        let res = entry.before.clone();
+        self.redo_stack.push(entry);
+        Some(res)
+    }
+
+    pub fn redo(&mut self) -> Option<TimeTable> {
+        let entry = self.redo_stack.pop()?;
+        let res = entry.after.clone();
+        self.undo_stack.push(entry);
+        Some(res)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    //新しい順
+    pub fn get_state(&self) -> HistoryState {
+        HistoryState {
+            undo: self.undo_stack.iter().rev().map(|x| x.kind).collect(),
+            redo: self.redo_stack.iter().rev().map(|x| x.kind).collect(),
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use super::cell::CellPosition;
+use super::{get_lock_color, get_pheromone_color, TimeTable};
+use crate::algorithm::aco::graph::{ClassLock, LockKind};
+use crate::algorithm::aco::aco_solver::ACOSolver;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+//1回の操作で動く授業1つ分
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedMove {
+    pub class_index: usize,
+    pub class_id: usize,
+    pub class_name: String,
+    pub from: CellPosition,
+    pub to: CellPosition,
+}
+
+//occupied[room][period]: そのコマにいる授業のindex
+struct Occupancy {
+    occupied: Vec<Vec<Option<usize>>>,
+}
+
+impl Occupancy {
+    fn new(timetable: &TimeTable, excluded: &BTreeSet<usize>) -> Occupancy {
+        let occupied = timetable
+            .process_table
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|x| x.as_ref().map(|x| x.index).filter(|x| !excluded.contains(x)))
+                    .collect()
+            })
+            .collect();
+        Occupancy { occupied }
+    }
+
+    fn fits(&self, position: CellPosition, size: usize) -> bool {
+        if position.room >= self.occupied.len() {
+            return false;
+        }
+        let row = &self.occupied[position.room];
+        if position.period + size > row.len() {
+            return false;
+        }
+        row[position.period..position.period + size]
+            .iter()
+            .all(|x| x.is_none())
+    }
+
+    fn place(&mut self, class_index: usize, position: CellPosition, size: usize) {
+        for period in position.period..position.period + size {
+            self.occupied[position.room][period] = Some(class_index);
+        }
+    }
+}
+
+fn get_size(timetable: &TimeTable, class_index: usize) -> usize {
+    timetable.class_list[class_index]
+        .as_ref()
+        .and_then(|x| x.size)
+        .unwrap_or(1)
+}
+
+fn create_move(timetable: &TimeTable, class_index: usize, to: CellPosition) -> PlannedMove {
+    let cell = timetable.class_list[class_index].as_ref().unwrap();
+    PlannedMove {
+        class_index,
+        class_id: cell.id,
+        class_name: cell.class_name.clone(),
+        from: cell.get_position(),
+        to,
+    }
+}
+
+fn get_lock(timetable: &TimeTable, class_index: usize) -> Option<ClassLock> {
+    timetable.class_list[class_index].as_ref()?.get_lock()
+}
+
+//部屋かコマだけ固定された授業は、固定された範囲の中でなら押し出せる
+fn is_allowed(timetable: &TimeTable, class_index: usize, position: CellPosition) -> bool {
+    get_lock(timetable, class_index)
+        .map(|x| x.allows(position.room, position.period))
+        .unwrap_or(true)
+}
+
+//連続授業が日を跨ぐ位置か
+fn straddles_days(period: usize, size: usize, day_length: usize) -> bool {
+    period % day_length + size > day_length
+}
+
+// 同じコマの別の教室を優先し、次に近いコマを探す。日を跨ぐ位置には置かない
+fn find_nearest_free(
+    timetable: &TimeTable,
+    occupancy: &Occupancy,
+    class_index: usize,
+    day_length: usize,
+) -> Option<CellPosition> {
+    let from = timetable.class_list[class_index].as_ref()?.get_position();
+    let size = get_size(timetable, class_index);
+    let candidates = timetable.process_table[from.room][from.period]
+        .as_ref()
+        .map(|x| x.room_candidates_indexes.clone())
+        .filter(|x| !x.is_empty())
+        .unwrap_or((0..timetable.room_size).collect());
+    let mut best: Option<(usize, usize, CellPosition)> = None;
+    for &room in candidates.iter() {
+        for period in 0..timetable.period_size {
+            let position = CellPosition { room, period };
+            if straddles_days(period, size, day_length)
+                || !occupancy.fits(position, size)
+                || !is_allowed(timetable, class_index, position)
+            {
+                continue;
+            }
+            let key = (
+                (period as i64 - from.period as i64).unsigned_abs() as usize,
+                (room as i64 - from.room as i64).unsigned_abs() as usize,
+            );
+            if best.map(|(a, b, _)| key < (a, b)).unwrap_or(true) {
+                best = Some((key.0, key.1, position));
+            }
+        }
+    }
+    best.map(|(_, _, position)| position)
+}
+
+// 空きコマへの移動 > 1つの授業との入れ替え > 邪魔な授業を近くの空きコマへ押し出す、の順に試す
+// 部屋とコマの両方がロックされた授業は動かさない
+pub fn plan_move(
+    timetable: &TimeTable,
+    class_index: usize,
+    to: CellPosition,
+    day_length: usize,
+) -> Option<Vec<PlannedMove>> {
+    let from = timetable.class_list.get(class_index)?.as_ref()?.get_position();
+    let size = get_size(timetable, class_index);
+    if to.room >= timetable.room_size || to.period + size > timetable.period_size {
+        return None;
+    }
+    if timetable.is_movable(class_index, to) {
+        return Some(vec![create_move(timetable, class_index, to)]);
+    }
+    let blockers = (to.period..to.period + size)
+        .filter_map(|period| timetable.process_table[to.room][period].as_ref())
+        .map(|x| x.index)
+        .filter(|&x| x != class_index)
+        .collect::<BTreeSet<usize>>();
+    let is_fixed = |index: usize| {
+        get_lock(timetable, index)
+            .map(|x| x.kind == LockKind::Both)
+            .unwrap_or(false)
+    };
+    if blockers.iter().any(|&x| is_fixed(x)) {
+        return None;
+    }
+
+    let mut excluded = blockers.clone();
+    excluded.insert(class_index);
+    if blockers.len() == 1 {
+        let blocker = *blockers.iter().next().unwrap();
+        let mut occupancy = Occupancy::new(timetable, &excluded);
+        occupancy.place(class_index, to, size);
+        let blocker_size = get_size(timetable, blocker);
+        if occupancy.fits(from, blocker_size)
+            && !straddles_days(from.period, blocker_size, day_length)
+            && is_allowed(timetable, blocker, from)
+        {
+            return Some(vec![
+                create_move(timetable, class_index, to),
+                create_move(timetable, blocker, from),
+            ]);
+        }
+    }
+
+    let mut occupancy = Occupancy::new(timetable, &excluded);
+    occupancy.place(class_index, to, size);
+    let mut moves = vec![create_move(timetable, class_index, to)];
+    //大きい授業から先に場所を決める
+    let mut blockers = blockers.into_iter().collect::<Vec<usize>>();
+    blockers.sort_by_key(|&x| std::cmp::Reverse(get_size(timetable, x)));
+    for blocker in blockers {
+        let position = find_nearest_free(timetable, &occupancy, blocker, day_length)?;
+        occupancy.place(blocker, position, get_size(timetable, blocker));
+        moves.push(create_move(timetable, blocker, position));
+    }
+    Some(moves)
+}
+
+//全部外してから置き直すので、移動先が互いの移動元でもよい
+pub fn apply_moves(timetable: &mut TimeTable, moves: &[PlannedMove], solver: &ACOSolver) {
+    let mut classes = Vec::new();
+    //移動元と移動先のコマにいる授業の違反だけ計算し直す
+    let mut periods = BTreeSet::<usize>::new();
+    for planned in moves.iter() {
+        let class = timetable
+            .get_class(planned.from.room, planned.from.period)
+            .unwrap();
+        periods.extend(planned.from.period..planned.from.period + class.serial_size);
+        periods.extend(planned.to.period..planned.to.period + class.serial_size);
+        let cell = timetable.class_list[planned.class_index].as_ref().unwrap();
+        classes.push((class, cell.is_locked, cell.lock_kind));
+    }
+    for planned in moves.iter() {
+        timetable.remove_class(planned.from.room, planned.from.period);
+    }
+    for (planned, (class, is_locked, lock_kind)) in moves.iter().zip(classes.into_iter()) {
+        let color = if is_locked.unwrap_or(false) {
+            get_lock_color(lock_kind.unwrap_or(LockKind::Both))
+        } else {
+            get_pheromone_color(solver, planned.class_index, planned.to.room, planned.to.period)
+        };
+        timetable.add_class(planned.to.room, planned.to.period, class, Some(color), solver);
+        let cell = timetable.class_list[planned.class_index].as_mut().unwrap();
+        cell.is_locked = is_locked;
+        cell.lock_kind = lock_kind;
+    }
+    timetable.update_violations_in_periods(
+        &periods,
+        solver.input.get_rooms(),
+        solver.parameters.num_of_day_lengths,
+    );
+}
+
+#[tauri::command]
+pub fn handle_get_move_plan(
+    session_manager: tauri::State<'_, SessionManager>,
+    class_id: usize,
+    to: CellPosition,
+) -> Result<Vec<PlannedMove>, CommandError> {
+    info!("called handle_get_move_plan,{},{:?}", class_id, to);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    let class_index = timetable
+        .get_class_index_by_id(class_id)
+        .ok_or(CommandError::ClassNotFound(class_id))?;
+    plan_move(timetable, class_index, to, solver.parameters.num_of_day_lengths)
+        .ok_or(CommandError::InvalidOperation(format!("class {} cannot be moved to {:?}", class_id, to)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::input_file::InputFile;
+
+    //教室2つ、1日5コマを2日分、serial_sizesの長さの授業
+    fn create_solver(serial_sizes: &[usize]) -> ACOSolver {
+        let classes = serial_sizes
+            .iter()
+            .enumerate()
+            .map(|(i, size)| {
+                format!(
+                    r#"{{"id": {}, "name": "C{}", "rooms": [10, 11], "numOfStudents": 0, "serialSize": {}}}"#,
+                    i, i, size
+                )
+            })
+            .collect::<Vec<String>>();
+        let json = format!(
+            r#"{{
+                "version": 1,
+                "teachers": [],
+                "studentGroups": [],
+                "rooms": [{{"id": 10, "name": "R1", "capacity": 40}}, {{"id": 11, "name": "R2", "capacity": 40}}],
+                "classes": [{}]
+            }}"#,
+            classes.join(",")
+        );
+        let file: InputFile = serde_json::from_str(&json).unwrap();
+        let input = file.into_input().unwrap();
+        let parameters = crate::sized_aco_parameters(&input, 10, 5);
+        crate::reset_aco_solver(&input, &parameters)
+    }
+
+    fn create_timetable(solver: &ACOSolver, positions: &[(usize, usize)]) -> TimeTable {
+        let mut timetable = TimeTable::new(
+            solver.parameters.num_of_rooms,
+            solver.parameters.num_of_periods,
+            solver.input.get_classes().len(),
+        );
+        for (class, &(room, period)) in solver.input.get_classes().iter().zip(positions.iter()) {
+            timetable.add_class(room, period, class.clone(), None, solver);
+        }
+        timetable
+    }
+
+    fn lock(timetable: &mut TimeTable, class_index: usize, kind: LockKind) {
+        let cell = timetable.class_list[class_index].as_mut().unwrap();
+        cell.is_locked = Some(true);
+        cell.lock_kind = Some(kind);
+    }
+
+    fn get_position(timetable: &TimeTable, class_index: usize) -> (usize, usize) {
+        let cell = timetable.class_list[class_index].as_ref().unwrap();
+        (cell.room, cell.period)
+    }
+
+    fn get_destinations(moves: &[PlannedMove]) -> Vec<(usize, usize, usize)> {
+        moves
+            .iter()
+            .map(|x| (x.class_index, x.to.room, x.to.period))
+            .collect()
+    }
+
+    //1つの授業に重なるときは、その授業を移動元へ入れ替える
+    #[test]
+    fn swap_with_single_blocker() {
+        let solver = create_solver(&[1, 1]);
+        let mut timetable = create_timetable(&solver, &[(0, 0), (0, 3)]);
+        let moves = plan_move(&timetable, 0, CellPosition { room: 0, period: 3 }, 5).unwrap();
+        assert_eq!(get_destinations(&moves), vec![(0, 0, 3), (1, 0, 0)]);
+
+        apply_moves(&mut timetable, &moves, &solver);
+        assert_eq!(get_position(&timetable, 0), (0, 3));
+        assert_eq!(get_position(&timetable, 1), (0, 0));
+        assert_eq!(timetable.process_table[0][3].as_ref().unwrap().index, 0);
+        assert_eq!(timetable.process_table[0][0].as_ref().unwrap().index, 1);
+    }
+
+    //部屋とコマの両方が固定された授業は押し出さない
+    #[test]
+    fn blocked_by_both_lock() {
+        let solver = create_solver(&[1, 1]);
+        let mut timetable = create_timetable(&solver, &[(0, 0), (0, 3)]);
+        lock(&mut timetable, 1, LockKind::Both);
+        assert!(plan_move(&timetable, 0, CellPosition { room: 0, period: 3 }, 5).is_none());
+    }
+
+    //コマだけ固定された授業は、同じコマの別の教室へ押し出す
+    #[test]
+    fn pushes_period_locked_blocker_to_another_room() {
+        let solver = create_solver(&[1, 1]);
+        let mut timetable = create_timetable(&solver, &[(0, 0), (0, 3)]);
+        lock(&mut timetable, 1, LockKind::PeriodOnly);
+        let moves = plan_move(&timetable, 0, CellPosition { room: 0, period: 3 }, 5).unwrap();
+        assert_eq!(get_destinations(&moves), vec![(0, 0, 3), (1, 1, 3)]);
+    }
+
+    //入れ替えると連続授業が日を跨ぐときは、入れ替えずに近くの空きコマへ押し出す
+    #[test]
+    fn does_not_swap_across_day_boundary() {
+        let solver = create_solver(&[1, 2]);
+        let timetable = create_timetable(&solver, &[(0, 4), (0, 0)]);
+        let moves = plan_move(&timetable, 0, CellPosition { room: 0, period: 0 }, 5).unwrap();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[1].class_index, 1);
+        assert!(!straddles_days(moves[1].to.period, 2, 5));
+        assert_ne!(moves[1].to, CellPosition { room: 0, period: 4 });
+    }
+
+    //複数の授業に重なるときは、それぞれ近くの空きコマへ押し出す
+    #[test]
+    fn pushes_multiple_blockers() {
+        let solver = create_solver(&[2, 1, 1]);
+        let mut timetable = create_timetable(&solver, &[(0, 5), (0, 0), (0, 1)]);
+        let moves = plan_move(&timetable, 0, CellPosition { room: 0, period: 0 }, 5).unwrap();
+        assert_eq!(get_destinations(&moves), vec![(0, 0, 0), (1, 1, 0), (2, 1, 1)]);
+
+        apply_moves(&mut timetable, &moves, &solver);
+        assert_eq!(get_position(&timetable, 0), (0, 0));
+        assert_eq!(get_position(&timetable, 1), (1, 0));
+        assert_eq!(get_position(&timetable, 2), (1, 1));
+        assert!(timetable.process_table[0][5].is_none());
+        assert!(timetable.process_table[0][6].is_none());
+    }
+}
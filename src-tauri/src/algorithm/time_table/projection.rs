@@ -0,0 +1,198 @@
+use super::cell::ActiveCell;
+use super::TimeTable;
+use crate::input::class::Class;
+use crate::input::Input;
+use log::info;
+use serde::{Deserialize, Serialize};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ViewKind {
+    Teacher,
+    StudentGroup,
+    Room,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectedEntry {
+    pub class_index: usize,
+    pub class_name: String,
+    pub room: usize,
+    pub room_name: String,
+    pub teachers: Vec<String>,
+    //教員の視点では自分以外の教員、それ以外では全教員
+    pub co_teachers: Vec<String>,
+    pub student_groups: Vec<String>,
+    pub period: usize,
+    pub is_violated: bool,
+}
+
+//slots[day][period in the day]、衝突があると1コマに複数のentryが入る
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectedTimeTable {
+    pub kind: ViewKind,
+    pub index: usize,
+    pub name: String,
+    pub num_of_days: usize,
+    pub num_of_day_lengths: usize,
+    pub slots: Vec<Vec<Vec<ProjectedEntry>>>,
+}
+
+pub fn is_in_view(class: &Class, cell: &ActiveCell, kind: ViewKind, index: usize) -> bool {
+    match kind {
+        ViewKind::Teacher => class.teacher_indexes.contains(&index),
+        ViewKind::StudentGroup => class.students_group_indexes.contains(&index),
+        ViewKind::Room => cell.room == index,
+    }
+}
+
+pub fn get_view_names(input: &Input, kind: ViewKind) -> Vec<String> {
+    match kind {
+        ViewKind::Teacher => input.get_teachers().iter().map(|x| x.name.clone()).collect(),
+        ViewKind::StudentGroup => input
+            .get_student_groups()
+            .iter()
+            .map(|x| x.name.clone())
+            .collect(),
+        ViewKind::Room => input.get_rooms().iter().map(|x| x.name.clone()).collect(),
+    }
+}
+
+pub fn project_timetable(
+    timetable: &TimeTable,
+    input: &Input,
+    num_of_day_lengths: usize,
+    kind: ViewKind,
+    index: usize,
+) -> Result<ProjectedTimeTable, CommandError> {
+    let name = get_view_names(input, kind)
+        .get(index)
+        .cloned()
+        .ok_or(CommandError::InvalidArgument(format!("{:?} not found: {}", kind, index)))?;
+    if num_of_day_lengths == 0 {
+        return Err(CommandError::InvalidArgument("a day has no periods".to_string()));
+    }
+    let num_of_days = timetable.period_size.div_ceil(num_of_day_lengths);
+    let mut slots = vec![vec![Vec::new(); num_of_day_lengths]; num_of_days];
+    for cell in timetable.class_list.iter().flatten() {
+        let class = &input.get_classes()[cell.class_index];
+        if !is_in_view(class, cell, kind, index) {
+            continue;
+        }
+        let teachers = class
+            .teacher_indexes
+            .iter()
+            .map(|&x| input.get_teachers()[x].name.clone())
+            .collect::<Vec<String>>();
+        let co_teachers = class
+            .teacher_indexes
+            .iter()
+            .filter(|&&x| kind != ViewKind::Teacher || x != index)
+            .map(|&x| input.get_teachers()[x].name.clone())
+            .collect::<Vec<String>>();
+        let student_groups = class
+            .students_group_indexes
+            .iter()
+            .map(|&x| input.get_student_groups()[x].name.clone())
+            .collect::<Vec<String>>();
+        let is_violated = cell
+            .violations
+            .as_ref()
+            .map(|x| x.is_violated)
+            .unwrap_or(false);
+        for i in 0..class.serial_size {
+            let period = cell.period + i;
+            if period >= timetable.period_size {
+                break;
+            }
+            slots[period / num_of_day_lengths][period % num_of_day_lengths].push(
+                ProjectedEntry {
+                    class_index: cell.class_index,
+                    class_name: class.name.clone(),
+                    room: cell.room,
+                    room_name: input.get_rooms()[cell.room].name.clone(),
+                    teachers: teachers.clone(),
+                    co_teachers: co_teachers.clone(),
+                    student_groups: student_groups.clone(),
+                    period,
+                    is_violated,
+                },
+            );
+        }
+    }
+    Ok(ProjectedTimeTable {
+        kind,
+        index,
+        name,
+        num_of_days,
+        num_of_day_lengths,
+        slots,
+    })
+}
+
+pub fn project_all_timetables(
+    timetable: &TimeTable,
+    input: &Input,
+    num_of_day_lengths: usize,
+    kind: ViewKind,
+) -> Result<Vec<ProjectedTimeTable>, CommandError> {
+    let mut res = Vec::new();
+    for index in 0..get_view_names(input, kind).len() {
+        res.push(project_timetable(
+            timetable,
+            input,
+            num_of_day_lengths,
+            kind,
+            index,
+        )?);
+    }
+    Ok(res)
+}
+
+#[tauri::command]
+pub fn handle_get_view_names(
+    session_manager: tauri::State<'_, SessionManager>,
+    kind: ViewKind,
+) -> Result<Vec<String>, CommandError> {
+    info!("called handle_get_view_names {:?}", kind);
+    let session = session_manager.session.lock_state()?;
+    Ok(get_view_names(&session.get_solver()?.input, kind))
+}
+
+#[tauri::command]
+pub fn handle_get_projected_timetable(
+    session_manager: tauri::State<'_, SessionManager>,
+    kind: ViewKind,
+    index: usize,
+) -> Result<ProjectedTimeTable, CommandError> {
+    info!("called handle_get_projected_timetable {:?} {}", kind, index);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    project_timetable(
+        timetable,
+        &solver.input,
+        solver.parameters.num_of_day_lengths,
+        kind,
+        index,
+    )
+}
+
+#[tauri::command]
+pub fn handle_get_all_projected_timetables(
+    session_manager: tauri::State<'_, SessionManager>,
+    kind: ViewKind,
+) -> Result<Vec<ProjectedTimeTable>, CommandError> {
+    info!("called handle_get_all_projected_timetables {:?}", kind);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    project_all_timetables(
+        timetable,
+        &solver.input,
+        solver.parameters.num_of_day_lengths,
+        kind,
+    )
+}
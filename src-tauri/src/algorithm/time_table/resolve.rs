@@ -0,0 +1,128 @@
+use super::cell::ActiveCell;
+use super::TimeTable;
+use crate::algorithm::aco::graph::LockKind;
+use crate::input::Input;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+//入力が変わる前の配置。部屋が削除された場合はroomがNoneになる
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviousAssignment {
+    pub room: Option<usize>,
+    pub period: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MovedClass {
+    pub class_index: usize,
+    pub class_name: String,
+    pub from_room: String,
+    pub from_period: usize,
+    pub to_room: String,
+    pub to_period: usize,
+}
+
+// old class id -> (old room index, period)
+fn get_assignments_by_class_id(timetable: &TimeTable) -> HashMap<usize, (usize, usize)> {
+    let mut res = HashMap::new();
+    for cell in timetable.class_list.iter().flatten() {
+        if let Some(class) = timetable.get_class(cell.room, cell.period) {
+            res.insert(class.id, (cell.room, cell.period));
+        }
+    }
+    res
+}
+
+//classとroomはindexではなくidで対応させる
+pub fn calc_previous_assignments(
+    previous_timetable: &TimeTable,
+    previous_input: &Input,
+    input: &Input,
+) -> Vec<Option<PreviousAssignment>> {
+    let assignments = get_assignments_by_class_id(previous_timetable);
+    let mut res = Vec::with_capacity(input.get_classes().len());
+    for class in input.get_classes().iter() {
+        if let Some(&(previous_room, period)) = assignments.get(&class.id) {
+            let room_id = previous_input.get_rooms()[previous_room].id;
+            let room = input.get_rooms().iter().position(|x| x.id == room_id);
+            res.push(Some(PreviousAssignment { room, period }));
+        } else {
+            res.push(None);
+        }
+    }
+    res
+}
+
+//固定していた授業を新しい入力のindexに付け替える、部屋が削除された場合はコマだけ固定する
+fn map_lock(
+    cell: &ActiveCell,
+    previous_input: &Input,
+    input: &Input,
+    num_of_periods: usize,
+) -> Option<ActiveCell> {
+    let lock = cell.get_lock()?;
+    let class_id = previous_input.get_classes()[cell.class_index].id;
+    let class_index = input.get_classes().iter().position(|x| x.id == class_id)?;
+    let room_id = previous_input.get_rooms()[cell.room].id;
+    let room = input.get_rooms().iter().position(|x| x.id == room_id);
+    let kind = match (lock.kind, room) {
+        (LockKind::RoomOnly, None) => return None,
+        (LockKind::Both, None) => LockKind::PeriodOnly,
+        (kind, _) => kind,
+    };
+    if kind != LockKind::RoomOnly
+        && cell.period + input.get_classes()[class_index].serial_size > num_of_periods
+    {
+        return None;
+    }
+    let mut res = cell.clone();
+    res.id = class_id;
+    res.class_index = class_index;
+    res.room = room.unwrap_or(0);
+    res.lock_kind = Some(kind);
+    Some(res)
+}
+
+//calc_previous_assignmentsと同じくidで対応させる
+pub fn calc_previous_locks(
+    previous_timetable: &TimeTable,
+    previous_input: &Input,
+    input: &Input,
+    num_of_periods: usize,
+) -> Vec<Option<ActiveCell>> {
+    let mut res = Vec::new();
+    for cell in previous_timetable.class_list.iter().flatten() {
+        res.push(map_lock(cell, previous_input, input, num_of_periods));
+    }
+    res
+}
+
+pub fn calc_moved_classes(
+    previous_timetable: &TimeTable,
+    previous_input: &Input,
+    timetable: &TimeTable,
+    input: &Input,
+) -> Vec<MovedClass> {
+    let previous_assignments = get_assignments_by_class_id(previous_timetable);
+    let mut res = Vec::new();
+    for cell in timetable.class_list.iter().flatten() {
+        let class = &input.get_classes()[cell.class_index];
+        if let Some(&(previous_room, previous_period)) = previous_assignments.get(&class.id) {
+            let previous_room = &previous_input.get_rooms()[previous_room];
+            let room = &input.get_rooms()[cell.room];
+            if previous_room.id != room.id || previous_period != cell.period {
+                res.push(MovedClass {
+                    class_index: cell.class_index,
+                    class_name: class.name.clone(),
+                    from_room: previous_room.name.clone(),
+                    from_period: previous_period,
+                    to_room: room.name.clone(),
+                    to_period: cell.period,
+                });
+            }
+        }
+    }
+    res
+}
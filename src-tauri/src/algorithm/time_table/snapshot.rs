@@ -0,0 +1,256 @@
+use super::history::EditKind;
+use super::TimeTable;
+use crate::algorithm::aco::aco_solver::ACOSolver;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub name: String,
+    pub created_at: u64,
+    pub timetable: TimeTable,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ViolationCount {
+    pub violated_cells: usize,
+    pub same_student_same_time: usize,
+    pub same_teacher_same_time: usize,
+    pub capacity_over: usize,
+    pub strabble_days: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotSummary {
+    pub name: String,
+    pub created_at: u64,
+    pub violations: ViolationCount,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassChange {
+    pub class_index: usize,
+    pub class_name: String,
+    pub from_room: usize,
+    pub from_period: usize,
+    pub to_room: usize,
+    pub to_period: usize,
+    pub room_changed: bool,
+    pub period_changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDiff {
+    pub from: String,
+    pub to: String,
+    pub moved_classes: Vec<ClassChange>,
+    pub room_changes: usize,
+    pub period_changes: usize,
+    pub violations_before: ViolationCount,
+    pub violations_after: ViolationCount,
+    pub violated_cells_delta: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SnapshotStore {
+    snapshots: Vec<Snapshot>,
+}
+
+pub fn count_violations(timetable: &TimeTable) -> ViolationCount {
+    let mut res = ViolationCount::default();
+    for cell in timetable.class_list.iter() {
+        if let Some(violations) = cell.as_ref().and_then(|x| x.violations.as_ref()) {
+            if violations.is_violated {
+                res.violated_cells += 1;
+            }
+            res.same_student_same_time += violations.same_student_same_time.len();
+            res.same_teacher_same_time += violations.same_teacher_same_time.len();
+            res.capacity_over += violations.capacity_over.len();
+            res.strabble_days += violations.strabble_days.len();
+        }
+    }
+    res
+}
+
+impl SnapshotStore {
+    //同じ名前のスナップショットは上書きする
+    pub fn save(&mut self, name: String, timetable: TimeTable) {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0);
+        let snapshot = Snapshot {
+            name,
+            created_at,
+            timetable,
+        };
+        if let Some(pos) = self.snapshots.iter().position(|x| x.name == snapshot.name) {
+            self.snapshots[pos] = snapshot;
+        } else {
+            self.snapshots.push(snapshot);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Snapshot> {
+        self.snapshots.iter().find(|x| x.name == name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Snapshot> {
+        let pos = self.snapshots.iter().position(|x| x.name == name)?;
+        Some(self.snapshots.remove(pos))
+    }
+
+    pub fn list(&self) -> Vec<SnapshotSummary> {
+        self.snapshots
+            .iter()
+            .map(|x| SnapshotSummary {
+                name: x.name.clone(),
+                created_at: x.created_at,
+                violations: count_violations(&x.timetable),
+            })
+            .collect()
+    }
+
+    pub fn diff(&self, from: &str, to: &str) -> Result<SnapshotDiff, CommandError> {
+        let from_snapshot = self.get(from).ok_or(CommandError::SnapshotNotFound(from.to_string()))?;
+        let to_snapshot = self.get(to).ok_or(CommandError::SnapshotNotFound(to.to_string()))?;
+        let mut diff = diff_timetables(&from_snapshot.timetable, &to_snapshot.timetable);
+        diff.from = from.to_string();
+        diff.to = to.to_string();
+        Ok(diff)
+    }
+
+    pub fn get_snapshots(&self) -> &Vec<Snapshot> {
+        &self.snapshots
+    }
+    pub fn set_snapshots(&mut self, snapshots: Vec<Snapshot>) {
+        self.snapshots = snapshots;
+    }
+}
+
+//同じ入力から作られた時間割同士をclass_indexで比較する
+pub fn diff_timetables(from: &TimeTable, to: &TimeTable) -> SnapshotDiff {
+    let mut moved_classes = Vec::new();
+    for (from_cell, to_cell) in from.class_list.iter().zip(to.class_list.iter()) {
+        if let (Some(from_cell), Some(to_cell)) = (from_cell, to_cell) {
+            let room_changed = from_cell.room != to_cell.room;
+            let period_changed = from_cell.period != to_cell.period;
+            if room_changed || period_changed {
+                moved_classes.push(ClassChange {
+                    class_index: to_cell.class_index,
+                    class_name: to_cell.class_name.clone(),
+                    from_room: from_cell.room,
+                    from_period: from_cell.period,
+                    to_room: to_cell.room,
+                    to_period: to_cell.period,
+                    room_changed,
+                    period_changed,
+                });
+            }
+        }
+    }
+    let violations_before = count_violations(from);
+    let violations_after = count_violations(to);
+    SnapshotDiff {
+        from: String::new(),
+        to: String::new(),
+        room_changes: moved_classes.iter().filter(|x| x.room_changed).count(),
+        period_changes: moved_classes.iter().filter(|x| x.period_changed).count(),
+        moved_classes,
+        violated_cells_delta: violations_after.violated_cells as i64
+            - violations_before.violated_cells as i64,
+        violations_before,
+        violations_after,
+    }
+}
+
+fn is_compatible(timetable: &TimeTable, solver: &ACOSolver) -> bool {
+    timetable.class_list.len() == solver.input.get_classes().len()
+        && timetable.room_size == solver.parameters.num_of_rooms
+        && timetable.period_size == solver.parameters.num_of_periods
+}
+
+#[tauri::command]
+pub fn handle_save_snapshot(
+    session_manager: tauri::State<'_, SessionManager>,
+    name: String,
+) -> Result<Vec<SnapshotSummary>, CommandError> {
+    info!("called handle_save_snapshot {}", name);
+    let mut session = session_manager.session.lock_state()?;
+    let timetable = session.get_timetable()?.clone();
+    session.snapshots.save(name, timetable);
+    Ok(session.snapshots.list())
+}
+
+#[tauri::command]
+pub fn handle_list_snapshots(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<Vec<SnapshotSummary>, CommandError> {
+    info!("called handle_list_snapshots");
+    Ok(session_manager.session.lock_state()?.snapshots.list())
+}
+
+#[tauri::command]
+pub fn handle_restore_snapshot(
+    session_manager: tauri::State<'_, SessionManager>,
+    name: String,
+    revision: Option<u64>,
+) -> Result<TimeTable, CommandError> {
+    info!("called handle_restore_snapshot {}", name);
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let snapshot = session
+        .snapshots
+        .get(&name)
+        .ok_or(CommandError::SnapshotNotFound(name.to_string()))?
+        .clone();
+    //別の入力で作った時間割は今のsolverでは扱えない
+    if !is_compatible(&snapshot.timetable, session.get_solver()?) {
+        return Err(CommandError::InvalidOperation(format!(
+            "snapshot {} was taken with a different input",
+            name
+        )));
+    }
+    match session.get_timetable() {
+        Ok(before) => {
+            let before = before.clone();
+            let (timetable, _) = session.get_editable()?;
+            *timetable = snapshot.timetable;
+            session.record_edit(EditKind::RestoreSnapshot, before)?;
+        }
+        Err(_) => session.set_timetable(snapshot.timetable)?,
+    }
+    session.clone_timetable()
+}
+
+#[tauri::command]
+pub fn handle_delete_snapshot(
+    session_manager: tauri::State<'_, SessionManager>,
+    name: String,
+) -> Result<Vec<SnapshotSummary>, CommandError> {
+    info!("called handle_delete_snapshot {}", name);
+    let mut session = session_manager.session.lock_state()?;
+    session
+        .snapshots
+        .remove(&name)
+        .ok_or(CommandError::SnapshotNotFound(name.to_string()))?;
+    Ok(session.snapshots.list())
+}
+
+#[tauri::command]
+pub fn handle_diff_snapshots(
+    session_manager: tauri::State<'_, SessionManager>,
+    from: String,
+    to: String,
+) -> Result<SnapshotDiff, CommandError> {
+    info!("called handle_diff_snapshots {} {}", from, to);
+    session_manager.session.lock_state()?.snapshots.diff(&from, &to)
+}
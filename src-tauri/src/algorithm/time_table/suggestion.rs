@@ -0,0 +1,126 @@
+use super::cell::CellPosition;
+use super::moves::{apply_moves, plan_move, PlannedMove};
+use super::snapshot::{count_violations, ViolationCount};
+use super::TimeTable;
+use crate::algorithm::aco::aco_solver::ACOSolver;
+use log::info;
+use serde::{Deserialize, Serialize};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+//授業をtoへ動かしたときの評価
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveSuggestion {
+    pub to: CellPosition,
+    //Ant::calc_edge_lengthと同じコストを動かす授業すべてについて足したもの、小さいほど良い
+    pub edge_length: f64,
+    pub pheromone: f64,
+    //pheromone^alpha * (q / edge_length)^beta
+    pub score: f64,
+    //違反の件数の増減、負なら改善
+    pub violation_delta: i64,
+    pub violated_cells_delta: i64,
+    pub moves: Vec<PlannedMove>,
+}
+
+fn get_total(count: &ViolationCount) -> i64 {
+    (count.same_student_same_time
+        + count.same_teacher_same_time
+        + count.capacity_over
+        + count.strabble_days) as i64
+}
+
+//動かした後の時間割で、動かした授業それぞれをその授業を外したantに置いたときのコスト
+//入れ替えや押し出しで動く授業とのぶつかりは動かした後の配置で数える
+fn calc_moves_edge_length(moved: &TimeTable, moves: &[PlannedMove], solver: &ACOSolver) -> f64 {
+    let graph = solver.colony.get_graph();
+    let mut res = 0.0;
+    for planned in moves.iter() {
+        let assignments = moved
+            .class_list
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                x.as_ref()
+                    .filter(|_| i != planned.class_index)
+                    .map(|x| [x.room, x.period])
+            })
+            .collect::<Vec<Option<[usize; 2]>>>();
+        let ant = solver.create_ant_from_assignments(&assignments);
+        res += ant
+            .calc_edge_cost_of(planned.class_index, planned.to.room, planned.to.period, graph)
+            .get_edge_length();
+    }
+    res
+}
+
+// 違反が減る順、同じならscoreが高い順に並べる
+pub fn suggest_moves(
+    timetable: &TimeTable,
+    class_index: usize,
+    solver: &ACOSolver,
+) -> Vec<MoveSuggestion> {
+    let current = match timetable.class_list.get(class_index).and_then(|x| x.as_ref()) {
+        Some(cell) => cell.get_position(),
+        None => return Vec::new(),
+    };
+    let graph = solver.colony.get_graph();
+    let parameters = &solver.parameters;
+    let before = count_violations(timetable);
+
+    let mut res = Vec::new();
+    for room in 0..timetable.room_size {
+        for period in 0..timetable.period_size {
+            let to = CellPosition { room, period };
+            if to == current {
+                continue;
+            }
+            let moves = match plan_move(timetable, class_index, to, parameters.num_of_day_lengths) {
+                Some(moves) => moves,
+                None => continue,
+            };
+            let mut moved = timetable.clone();
+            apply_moves(&mut moved, &moves, solver);
+            let after = count_violations(&moved);
+            let edge_length = calc_moves_edge_length(&moved, &moves, solver);
+            let pheromone = graph.get_pheromone(class_index, room, period);
+            res.push(MoveSuggestion {
+                to,
+                edge_length,
+                pheromone,
+                score: pheromone.powf(parameters.alpha)
+                    * (parameters.q / edge_length).powf(parameters.beta),
+                violation_delta: get_total(&after) - get_total(&before),
+                violated_cells_delta: after.violated_cells as i64 - before.violated_cells as i64,
+                moves,
+            });
+        }
+    }
+    res.sort_by(|a, b| {
+        a.violation_delta
+            .cmp(&b.violation_delta)
+            .then(b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    res
+}
+
+//limitを省略すると候補をすべて返す
+#[tauri::command]
+pub fn handle_get_move_suggestions(
+    session_manager: tauri::State<'_, SessionManager>,
+    class_id: usize,
+    limit: Option<usize>,
+) -> Result<Vec<MoveSuggestion>, CommandError> {
+    info!("called handle_get_move_suggestions,{},{:?}", class_id, limit);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    let class_index = timetable
+        .get_class_index_by_id(class_id)
+        .ok_or(CommandError::ClassNotFound(class_id))?;
+    let mut suggestions = suggest_moves(timetable, class_index, solver);
+    if let Some(limit) = limit {
+        suggestions.truncate(limit);
+    }
+    Ok(suggestions)
+}
@@ -0,0 +1,131 @@
+use super::cell::CellPosition;
+use super::TimeTable;
+use crate::algorithm::aco::aco_solver::ACOSolver;
+use crate::algorithm::aco::edge_cost::EdgeCost;
+use log::info;
+use serde::{Deserialize, Serialize};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+// 確率の低い方からcount個、かつthreshold以下の授業を弱い配置とする
+// どちらもNoneなら全部の授業が対象
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WeakPlacementConfig {
+    pub count: Option<usize>,
+    pub threshold: Option<f64>,
+}
+
+impl Default for WeakPlacementConfig {
+    //以前のworst 3と同じ
+    fn default() -> Self {
+        WeakPlacementConfig {
+            count: Some(3),
+            threshold: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WeakPlacement {
+    pub class_id: usize,
+    pub class_index: usize,
+    pub class_name: String,
+    pub position: CellPosition,
+    //今の位置が選ばれる確率、他の授業は今の時間割の配置のまま
+    pub probability: f64,
+    //1始まり、確率が低いほど小さい
+    pub rank: usize,
+    pub is_weak: bool,
+    pub edge_length: f64,
+    pub costs: EdgeCost,
+}
+
+//確率の低い順に全部の授業を返す
+pub fn analyze_weak_placements(
+    timetable: &TimeTable,
+    solver: &ACOSolver,
+    config: &WeakPlacementConfig,
+) -> Vec<WeakPlacement> {
+    let graph = solver.colony.get_graph();
+    let assignments = timetable
+        .class_list
+        .iter()
+        .map(|x| x.as_ref().map(|x| [x.room, x.period]))
+        .collect::<Vec<Option<[usize; 2]>>>();
+    let mut ant = solver.create_ant_from_assignments(&assignments);
+    let mut res = Vec::new();
+    for cell in timetable.class_list.iter().flatten() {
+        //自分自身との重複を数えないように外して評価し、終わったら戻す
+        ant.deallocate_classes(cell.class_index, graph);
+        let (to_vertexes, to_prob) = ant.calc_prob_from_v_igunore_visited(cell.class_index, graph);
+        let probability = to_vertexes
+            .iter()
+            .position(|x| x[0] == cell.room && x[1] == cell.period)
+            .map(|i| to_prob[i])
+            .unwrap_or(0.0);
+        let costs = ant.calc_edge_cost_of(cell.class_index, cell.room, cell.period, graph);
+        ant.allocate_classes(cell.class_index, cell.room, cell.period, graph);
+        res.push(WeakPlacement {
+            class_id: cell.id,
+            class_index: cell.class_index,
+            class_name: cell.class_name.clone(),
+            position: cell.get_position(),
+            probability,
+            rank: 0,
+            is_weak: false,
+            edge_length: costs.get_edge_length(),
+            costs,
+        });
+    }
+    res.sort_by(|a, b| {
+        a.probability
+            .partial_cmp(&b.probability)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    for (i, placement) in res.iter_mut().enumerate() {
+        placement.rank = i + 1;
+        placement.is_weak = config.count.map(|x| i < x).unwrap_or(true)
+            && config
+                .threshold
+                .map(|x| placement.probability <= x)
+                .unwrap_or(true);
+    }
+    res
+}
+
+#[tauri::command]
+pub fn handle_get_weak_placements(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<Vec<WeakPlacement>, CommandError> {
+    info!("called handle_get_weak_placements");
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    Ok(analyze_weak_placements(timetable, solver, &session.weak_placement))
+}
+
+#[tauri::command]
+pub fn handle_get_weak_placement_config(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<WeakPlacementConfig, CommandError> {
+    info!("called handle_get_weak_placement_config");
+    Ok(session_manager.session.lock_state()?.weak_placement.clone())
+}
+
+//設定を変えたら今の時間割の印も付け直す
+#[tauri::command]
+pub fn handle_set_weak_placement_config(
+    session_manager: tauri::State<'_, SessionManager>,
+    config: WeakPlacementConfig,
+) -> Result<Option<TimeTable>, CommandError> {
+    info!("called handle_set_weak_placement_config {:?}", config);
+    let mut session = session_manager.session.lock_state()?;
+    session.weak_placement = config;
+    if session.get_timetable().is_err() {
+        return Ok(None);
+    }
+    session.annotate()?;
+    session.touch();
+    Ok(Some(session.clone_timetable()?))
+}
@@ -0,0 +1,139 @@
+// tauri commandが返すエラー
+// フロントエンドには {"code": "noTimetable", "message": "..."} の形で渡る
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    NoInput,
+    NoParameters,
+    NoSolver,
+    //入力を読み直した後、solverを作り直していない
+    OutdatedSolver,
+    NoTimetable,
+    //solverを走らせていないなど、時間割がまだ作れない
+    NoSolution,
+    ClassNotFound(usize),
+    SnapshotNotFound(String),
+    InvalidArgument(String),
+    //移動やロックなど、今の状態ではできない操作
+    InvalidOperation(String),
+    Io(String),
+    Parse(String),
+    //UIが見ている時間割より後にsessionが変わっていた
+    StaleRevision { expected: u64, current: u64 },
+    //以前の操作でpanicしてMutexが使えなくなった
+    StatePoisoned,
+    Other(String),
+}
+
+impl CommandError {
+    pub fn get_code(&self) -> &'static str {
+        match self {
+            CommandError::NoInput => "noInput",
+            CommandError::NoParameters => "noParameters",
+            CommandError::NoSolver => "noSolver",
+            CommandError::OutdatedSolver => "outdatedSolver",
+            CommandError::NoTimetable => "noTimetable",
+            CommandError::NoSolution => "noSolution",
+            CommandError::ClassNotFound(_) => "classNotFound",
+            CommandError::SnapshotNotFound(_) => "snapshotNotFound",
+            CommandError::InvalidArgument(_) => "invalidArgument",
+            CommandError::InvalidOperation(_) => "invalidOperation",
+            CommandError::Io(_) => "io",
+            CommandError::Parse(_) => "parse",
+            CommandError::StaleRevision { .. } => "staleRevision",
+            CommandError::StatePoisoned => "statePoisoned",
+            CommandError::Other(_) => "other",
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::NoInput => write!(f, "no input loaded"),
+            CommandError::NoParameters => write!(f, "no ACO parameters"),
+            CommandError::NoSolver => write!(f, "no solver found"),
+            CommandError::OutdatedSolver => write!(f, "solver was built from an older input"),
+            CommandError::NoTimetable => write!(f, "no timetable found"),
+            CommandError::NoSolution => write!(f, "no solution found"),
+            CommandError::ClassNotFound(id) => write!(f, "class not found: {}", id),
+            CommandError::SnapshotNotFound(name) => write!(f, "snapshot not found: {}", name),
+            CommandError::InvalidArgument(message)
+            | CommandError::InvalidOperation(message)
+            | CommandError::Io(message)
+            | CommandError::Parse(message)
+            | CommandError::Other(message) => write!(f, "{}", message),
+            CommandError::StaleRevision { expected, current } => write!(
+                f,
+                "timetable has changed: expected revision {}, current revision {}",
+                expected, current
+            ),
+            CommandError::StatePoisoned => write!(f, "internal state is poisoned"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CommandError", 2)?;
+        state.serialize_field("code", self.get_code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Other(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::Other(message.to_string())
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(error: std::io::Error) -> Self {
+        CommandError::Io(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for CommandError {
+    fn from(error: serde_json::Error) -> Self {
+        CommandError::Parse(error.to_string())
+    }
+}
+
+//読み込みや書き出しの関数はBox<dyn Error>を返すので、中身で振り分ける
+impl From<Box<dyn std::error::Error>> for CommandError {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        if let Some(error) = error.downcast_ref::<std::io::Error>() {
+            return CommandError::Io(error.to_string());
+        }
+        if let Some(error) = error.downcast_ref::<serde_json::Error>() {
+            return CommandError::Parse(error.to_string());
+        }
+        if let Some(error) = error.downcast_ref::<CommandError>() {
+            return error.clone();
+        }
+        CommandError::Other(error.to_string())
+    }
+}
+
+pub trait LockState<T> {
+    fn lock_state(&self) -> Result<MutexGuard<'_, T>, CommandError>;
+}
+
+impl<T> LockState<T> for Mutex<T> {
+    fn lock_state(&self) -> Result<MutexGuard<'_, T>, CommandError> {
+        self.lock().map_err(|_| CommandError::StatePoisoned)
+    }
+}
@@ -0,0 +1,42 @@
+pub mod ical;
+pub mod report;
+pub mod sheet;
+
+use crate::algorithm::time_table::projection::ViewKind;
+use std::path::{Path, PathBuf};
+
+//ファイル名に使えない文字を置き換える
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+pub fn get_view_prefix(kind: ViewKind) -> &'static str {
+    match kind {
+        ViewKind::Teacher => "teacher",
+        ViewKind::StudentGroup => "group",
+        ViewKind::Room => "room",
+    }
+}
+
+//同じ名前や、置き換えた後に同じになる名前があっても上書きしないようにindexを付ける
+pub fn get_export_path(
+    dir: &Path,
+    kind: ViewKind,
+    index: usize,
+    name: &str,
+    extension: &str,
+) -> PathBuf {
+    dir.join(format!(
+        "{}_{}_{}.{}",
+        get_view_prefix(kind),
+        index,
+        sanitize_file_name(name),
+        extension
+    ))
+}
@@ -0,0 +1,281 @@
+use super::get_export_path;
+use crate::algorithm::time_table::projection::{get_view_names, is_in_view, ViewKind};
+use crate::algorithm::time_table::TimeTable;
+use crate::input::Input;
+use chrono::{Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+//一日の中の何限目が何時から何時までか
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodTime {
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IcalConfig {
+    //YYYY-MM-DD
+    pub term_start: String,
+    pub term_weeks: usize,
+    //HH:MM、num_of_day_lengths個
+    pub period_times: Vec<PeriodTime>,
+    //DTSTART/DTENDのTZID、VTIMEZONEは夏時間のない固定のオフセットで書き出す
+    #[serde(default = "get_default_time_zone")]
+    pub time_zone: String,
+    //+HH:MM
+    #[serde(default = "get_default_utc_offset")]
+    pub utc_offset: String,
+}
+
+fn get_default_time_zone() -> String {
+    "Asia/Tokyo".to_string()
+}
+
+fn get_default_utc_offset() -> String {
+    "+09:00".to_string()
+}
+
+struct ParsedPeriodTime {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+struct ParsedConfig {
+    term_start: NaiveDate,
+    period_times: Vec<ParsedPeriodTime>,
+    utc_offset: FixedOffset,
+}
+
+fn parse_config(
+    config: &IcalConfig,
+    num_of_day_lengths: usize,
+) -> Result<ParsedConfig, Box<dyn Error>> {
+    if num_of_day_lengths == 0 {
+        return Err("a day has no periods".into());
+    }
+    if config.term_weeks == 0 {
+        return Err("term weeks must be at least 1".into());
+    }
+    if config.time_zone.is_empty() {
+        return Err("time zone is empty".into());
+    }
+    let term_start = NaiveDate::parse_from_str(&config.term_start, "%Y-%m-%d")
+        .map_err(|e| format!("invalid term start {}: {}", config.term_start, e))?;
+    let utc_offset = config
+        .utc_offset
+        .parse::<FixedOffset>()
+        .map_err(|e| format!("invalid utc offset {}: {}", config.utc_offset, e))?;
+    if config.period_times.len() < num_of_day_lengths {
+        return Err(format!(
+            "period times has {} entries but a day has {} periods",
+            config.period_times.len(),
+            num_of_day_lengths
+        )
+        .into());
+    }
+    let mut period_times = Vec::new();
+    for period_time in config.period_times.iter() {
+        let start = NaiveTime::parse_from_str(&period_time.start, "%H:%M")
+            .map_err(|e| format!("invalid period start {}: {}", period_time.start, e))?;
+        let end = NaiveTime::parse_from_str(&period_time.end, "%H:%M")
+            .map_err(|e| format!("invalid period end {}: {}", period_time.end, e))?;
+        period_times.push(ParsedPeriodTime { start, end });
+    }
+    Ok(ParsedConfig {
+        term_start,
+        period_times,
+        utc_offset,
+    })
+}
+
+//+09:00 -> +0900
+fn format_utc_offset(offset: &FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// RFC 5545: lines longer than 75 octets are folded
+fn fold_line(line: &str) -> String {
+    let mut res = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            res.push_str("\r\n ");
+            width = 1;
+        }
+        res.push(c);
+        width += c.len_utf8();
+    }
+    res.push_str("\r\n");
+    res
+}
+
+pub fn create_ical(
+    timetable: &TimeTable,
+    input: &Input,
+    num_of_day_lengths: usize,
+    config: &IcalConfig,
+    kind: ViewKind,
+    index: usize,
+) -> Result<String, Box<dyn Error>> {
+    let ParsedConfig {
+        term_start,
+        period_times,
+        utc_offset,
+    } = parse_config(config, num_of_day_lengths)?;
+    let name = get_view_names(input, kind)
+        .get(index)
+        .cloned()
+        .ok_or(CommandError::InvalidArgument(format!("{:?} not found: {}", kind, index)))?;
+    let dtstamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    //TZIDつきのDTSTARTに対してUNTILはUTCで書く、学期の最終日の終わりまで
+    let until = (term_start + Duration::days(7 * config.term_weeks as i64))
+        .and_time(NaiveTime::MIN)
+        .and_local_timezone(utc_offset)
+        .single()
+        .ok_or(format!("invalid term end for {}", config.utc_offset))?
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string();
+    let offset = format_utc_offset(&utc_offset);
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//ClassistAnt//Timetable//JA".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(&name)),
+        "BEGIN:VTIMEZONE".to_string(),
+        format!("TZID:{}", config.time_zone),
+        "BEGIN:STANDARD".to_string(),
+        "DTSTART:19700101T000000".to_string(),
+        format!("TZOFFSETFROM:{}", offset),
+        format!("TZOFFSETTO:{}", offset),
+        "END:STANDARD".to_string(),
+        "END:VTIMEZONE".to_string(),
+    ];
+    for cell in timetable.class_list.iter().flatten() {
+        let class = &input.get_classes()[cell.class_index];
+        if !is_in_view(class, cell, kind, index) {
+            continue;
+        }
+        //曜日は月曜始まり、日を跨ぐコマはその日の最後のコマまで
+        let day = cell.period / num_of_day_lengths;
+        if day >= 7 {
+            return Err(format!("period {} is not in a week", cell.period).into());
+        }
+        let first_period = cell.period % num_of_day_lengths;
+        let last_period = (first_period + class.serial_size.max(1) - 1).min(num_of_day_lengths - 1);
+        let days_from_term_start =
+            (day + 7 - term_start.weekday().num_days_from_monday() as usize) % 7;
+        let date = term_start + Duration::days(days_from_term_start as i64);
+        let start = date.and_time(period_times[first_period].start);
+        let end = date.and_time(period_times[last_period].end);
+        let teachers = class
+            .teacher_indexes
+            .iter()
+            .map(|&x| input.get_teachers()[x].name.clone())
+            .collect::<Vec<String>>();
+        let student_groups = class
+            .students_group_indexes
+            .iter()
+            .map(|&x| input.get_student_groups()[x].name.clone())
+            .collect::<Vec<String>>();
+        let room = &input.get_rooms()[cell.room].name;
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:{}-{}-{}@classistant",
+            cell.class_index,
+            super::get_view_prefix(kind),
+            index
+        ));
+        lines.push(format!("DTSTAMP:{}", dtstamp));
+        lines.push(format!(
+            "DTSTART;TZID={}:{}",
+            config.time_zone,
+            start.format("%Y%m%dT%H%M%S")
+        ));
+        lines.push(format!(
+            "DTEND;TZID={}:{}",
+            config.time_zone,
+            end.format("%Y%m%dT%H%M%S")
+        ));
+        lines.push(format!("RRULE:FREQ=WEEKLY;UNTIL={}", until));
+        lines.push(format!("SUMMARY:{}", escape_text(&class.name)));
+        lines.push(format!("LOCATION:{}", escape_text(room)));
+        lines.push(format!(
+            "DESCRIPTION:{}",
+            escape_text(&format!(
+                "教員: {}\n学生: {}\n教室: {}",
+                teachers.join(", "),
+                student_groups.join(", "),
+                room
+            ))
+        ));
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    Ok(lines.iter().map(|x| fold_line(x)).collect())
+}
+
+//教員・学生グループ・教室ごとに1つの.icsを書き出す
+pub fn export_ical(
+    timetable: &TimeTable,
+    input: &Input,
+    num_of_day_lengths: usize,
+    config: &IcalConfig,
+    kind: ViewKind,
+    dir: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    std::fs::create_dir_all(dir)?;
+    let mut res = Vec::new();
+    for (index, name) in get_view_names(input, kind).iter().enumerate() {
+        let ical = create_ical(timetable, input, num_of_day_lengths, config, kind, index)?;
+        let path = get_export_path(dir, kind, index, name, "ics");
+        let mut file = File::create(&path)?;
+        file.write_all(ical.as_bytes())?;
+        res.push(path);
+    }
+    Ok(res)
+}
+
+#[tauri::command]
+pub fn handle_export_ical(
+    session_manager: tauri::State<'_, SessionManager>,
+    config: IcalConfig,
+    kind: ViewKind,
+    dir: String,
+) -> Result<Vec<String>, CommandError> {
+    info!("called handle_export_ical {:?} {}", kind, dir);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    let paths = export_ical(
+        timetable,
+        &solver.input,
+        solver.parameters.num_of_day_lengths,
+        &config,
+        kind,
+        Path::new(&dir),
+    )?;
+    Ok(paths
+        .iter()
+        .map(|x| x.to_string_lossy().to_string())
+        .collect())
+}
@@ -0,0 +1,453 @@
+use crate::algorithm::aco::aco_parameters::{get_day_label, get_period_labels, AcoParameters};
+use crate::algorithm::aco::violations::Violations;
+use crate::algorithm::time_table::projection::{
+    project_all_timetables, ProjectedEntry, ProjectedTimeTable, ViewKind,
+};
+use crate::algorithm::time_table::TimeTable;
+use crate::input::Input;
+use log::info;
+use printpdf::{
+    Color, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    Point, Rect, Rgb,
+};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+static REPORT_KINDS: [ViewKind; 3] = [ViewKind::Room, ViewKind::Teacher, ViewKind::StudentGroup];
+
+//付録に載せる制約違反1件分
+pub struct ViolationRow {
+    pub class_name: String,
+    pub period_label: String,
+    pub room_name: String,
+    pub kind_label: String,
+    pub detail: String,
+}
+
+fn get_view_label(kind: ViewKind) -> &'static str {
+    match kind {
+        ViewKind::Teacher => "教員",
+        ViewKind::StudentGroup => "学生",
+        ViewKind::Room => "教室",
+    }
+}
+
+//1コマに表示する行、視点に応じて自明な情報は省く
+fn get_entry_lines(entry: &ProjectedEntry, kind: ViewKind) -> Vec<String> {
+    let mut lines = vec![entry.class_name.clone()];
+    if kind != ViewKind::Room {
+        lines.push(entry.room_name.clone());
+    }
+    if !entry.co_teachers.is_empty() {
+        lines.push(entry.co_teachers.join(", "));
+    }
+    if kind != ViewKind::StudentGroup && !entry.student_groups.is_empty() {
+        lines.push(entry.student_groups.join(", "));
+    }
+    lines
+}
+
+fn get_conflict_names(timetable: &TimeTable, violations: &[Violations]) -> String {
+    let mut names = Vec::new();
+    for violation in violations.iter() {
+        for &room in violation.rooms.iter() {
+            if let Some(class) = &timetable.process_table[room][violation.period] {
+                if !names.contains(&class.name) {
+                    names.push(class.name.clone());
+                }
+            }
+        }
+    }
+    names.join(", ")
+}
+
+pub fn create_violation_rows(
+    timetable: &TimeTable,
+    input: &Input,
+    period_labels: &[String],
+) -> Vec<ViolationRow> {
+    let mut rows = Vec::new();
+    for cell in timetable.class_list.iter() {
+        let cell = match cell {
+            Some(cell) => cell,
+            None => continue,
+        };
+        let violations = match &cell.violations {
+            Some(violations) if violations.is_violated => violations,
+            _ => continue,
+        };
+        let class = &input.get_classes()[cell.class_index];
+        let room = &input.get_rooms()[cell.room];
+        let mut push = |kind_label: &str, detail: String| {
+            rows.push(ViolationRow {
+                class_name: class.name.clone(),
+                period_label: period_labels.get(cell.period).cloned().unwrap_or_default(),
+                room_name: room.name.clone(),
+                kind_label: kind_label.to_string(),
+                detail,
+            });
+        };
+        if !violations.same_student_same_time.is_empty() {
+            push(
+                "学生の重複",
+                get_conflict_names(timetable, &violations.same_student_same_time),
+            );
+        }
+        if !violations.same_teacher_same_time.is_empty() {
+            push(
+                "教員の重複",
+                get_conflict_names(timetable, &violations.same_teacher_same_time),
+            );
+        }
+        if !violations.capacity_over.is_empty() {
+            push(
+                "収容人数超過",
+                format!("{}人 / 定員{}人", class.num_of_students, room.capacity),
+            );
+        }
+        if !violations.strabble_days.is_empty() {
+            push("日を跨ぐ連続授業", format!("{}コマ連続", class.serial_size));
+        }
+    }
+    rows
+}
+
+fn create_report_pages(
+    timetable: &TimeTable,
+    input: &Input,
+    parameters: &AcoParameters,
+) -> Result<Vec<ProjectedTimeTable>, CommandError> {
+    let mut pages = Vec::new();
+    for kind in REPORT_KINDS {
+        pages.extend(project_all_timetables(
+            timetable,
+            input,
+            parameters.num_of_day_lengths,
+            kind,
+        )?);
+    }
+    Ok(pages)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+static HTML_STYLE: &str = "
+body { font-family: sans-serif; margin: 0; }
+section { padding: 10mm; page-break-after: always; }
+h1 { font-size: 16pt; margin: 0 0 4mm 0; }
+table { border-collapse: collapse; width: 100%; table-layout: fixed; }
+th, td { border: 1px solid #333; padding: 2px 4px; font-size: 9pt; vertical-align: top; }
+th { background: #eee; }
+.entry { margin-bottom: 2px; }
+.entry div:first-child { font-weight: bold; }
+.violated { background: #fdd; }
+@page { size: A4 landscape; margin: 0; }
+";
+
+fn create_html_page(projected: &ProjectedTimeTable) -> String {
+    let mut html = String::new();
+    html.push_str("<section>\n");
+    html.push_str(&format!(
+        "<h1>{}: {}</h1>\n<table>\n<tr><th></th>",
+        get_view_label(projected.kind),
+        escape_html(&projected.name)
+    ));
+    for day in 0..projected.num_of_days {
+        html.push_str(&format!("<th>{}</th>", get_day_label(day)));
+    }
+    html.push_str("</tr>\n");
+    for period_in_day in 0..projected.num_of_day_lengths {
+        html.push_str(&format!("<tr><th>{}限</th>", period_in_day + 1));
+        for day in 0..projected.num_of_days {
+            let entries = &projected.slots[day][period_in_day];
+            let is_violated = entries.iter().any(|x| x.is_violated) || entries.len() > 1;
+            html.push_str(if is_violated {
+                "<td class=\"violated\">"
+            } else {
+                "<td>"
+            });
+            for entry in entries.iter() {
+                html.push_str("<div class=\"entry\">");
+                for line in get_entry_lines(entry, projected.kind) {
+                    html.push_str(&format!("<div>{}</div>", escape_html(&line)));
+                }
+                html.push_str("</div>");
+            }
+            html.push_str("</td>");
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n</section>\n");
+    html
+}
+
+fn create_html_appendix(rows: &[ViolationRow]) -> String {
+    let mut html = String::new();
+    html.push_str("<section>\n<h1>付録: 制約違反</h1>\n");
+    if rows.is_empty() {
+        html.push_str("<p>制約違反はありません</p>\n");
+    } else {
+        html.push_str(
+            "<table>\n<tr><th>授業</th><th>コマ</th><th>教室</th><th>種類</th><th>詳細</th></tr>\n",
+        );
+        for row in rows.iter() {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&row.class_name),
+                escape_html(&row.period_label),
+                escape_html(&row.room_name),
+                escape_html(&row.kind_label),
+                escape_html(&row.detail)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+//全ての教室・教員・学生グループの時間割を1ページずつ並べた印刷用HTML
+pub fn create_html_report(
+    timetable: &TimeTable,
+    input: &Input,
+    parameters: &AcoParameters,
+) -> Result<String, CommandError> {
+    let pages = create_report_pages(timetable, input, parameters)?;
+    let rows = create_violation_rows(timetable, input, &get_period_labels(parameters));
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>時間割</title>\n");
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", HTML_STYLE));
+    for page in pages.iter() {
+        html.push_str(&create_html_page(page));
+    }
+    html.push_str(&create_html_appendix(&rows));
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+// A4 landscape
+static PAGE_WIDTH: f32 = 297.0;
+static PAGE_HEIGHT: f32 = 210.0;
+static MARGIN: f32 = 10.0;
+static TITLE_HEIGHT: f32 = 12.0;
+static LABEL_WIDTH: f32 = 16.0;
+static HEADER_HEIGHT: f32 = 7.0;
+static FONT_SIZE: f32 = 7.0;
+static LINE_HEIGHT: f32 = 3.2;
+static PT_TO_MM: f32 = 0.3528;
+
+//文字幅は測れないので全角1文字をフォントサイズ分として切り詰める
+fn fit_text(text: &str, width: f32, font_size: f32) -> String {
+    let max_chars = (width / (font_size * PT_TO_MM)).floor().max(1.0) as usize;
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    text.chars().take(max_chars.saturating_sub(1)).collect::<String>() + "…"
+}
+
+fn draw_rect(layer: &PdfLayerReference, x: f32, y: f32, width: f32, height: f32) {
+    let points = vec![
+        (Point::new(Mm(x), Mm(y)), false),
+        (Point::new(Mm(x + width), Mm(y)), false),
+        (Point::new(Mm(x + width), Mm(y - height)), false),
+        (Point::new(Mm(x), Mm(y - height)), false),
+    ];
+    layer.add_line(Line {
+        points,
+        is_closed: true,
+    });
+}
+
+fn fill_rect(layer: &PdfLayerReference, x: f32, y: f32, width: f32, height: f32, color: Rgb) {
+    layer.set_fill_color(Color::Rgb(color));
+    layer.add_rect(Rect::new(Mm(x), Mm(y - height), Mm(x + width), Mm(y)));
+    layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+}
+
+fn add_page(doc: &PdfDocumentReference, title: &str, font: &IndirectFontRef) -> PdfLayerReference {
+    let (page, layer) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    doc.add_bookmark(title, page);
+    let layer = doc.get_page(page).get_layer(layer);
+    layer.set_outline_thickness(0.5);
+    layer.use_text(title, 14.0, Mm(MARGIN), Mm(PAGE_HEIGHT - MARGIN - 5.0), font);
+    layer
+}
+
+fn draw_pdf_page(doc: &PdfDocumentReference, projected: &ProjectedTimeTable, font: &IndirectFontRef) {
+    let title = format!("{}: {}", get_view_label(projected.kind), projected.name);
+    let layer = add_page(doc, &title, font);
+    let top = PAGE_HEIGHT - MARGIN - TITLE_HEIGHT;
+    let cell_width = (PAGE_WIDTH - MARGIN * 2.0 - LABEL_WIDTH) / projected.num_of_days.max(1) as f32;
+    let cell_height = (top - MARGIN - HEADER_HEIGHT) / projected.num_of_day_lengths.max(1) as f32;
+    let max_lines = ((cell_height - 1.0) / LINE_HEIGHT).floor().max(1.0) as usize;
+    for day in 0..projected.num_of_days {
+        let x = MARGIN + LABEL_WIDTH + cell_width * day as f32;
+        draw_rect(&layer, x, top, cell_width, HEADER_HEIGHT);
+        layer.use_text(get_day_label(day), 9.0, Mm(x + 1.5), Mm(top - 5.0), font);
+    }
+    for period_in_day in 0..projected.num_of_day_lengths {
+        let y = top - HEADER_HEIGHT - cell_height * period_in_day as f32;
+        draw_rect(&layer, MARGIN, y, LABEL_WIDTH, cell_height);
+        layer.use_text(
+            format!("{}限", period_in_day + 1),
+            9.0,
+            Mm(MARGIN + 1.5),
+            Mm(y - 5.0),
+            font,
+        );
+        for day in 0..projected.num_of_days {
+            let x = MARGIN + LABEL_WIDTH + cell_width * day as f32;
+            let entries = &projected.slots[day][period_in_day];
+            if entries.iter().any(|x| x.is_violated) || entries.len() > 1 {
+                fill_rect(&layer, x, y, cell_width, cell_height, Rgb::new(1.0, 0.87, 0.87, None));
+            }
+            draw_rect(&layer, x, y, cell_width, cell_height);
+            let lines = entries
+                .iter()
+                .flat_map(|entry| get_entry_lines(entry, projected.kind))
+                .collect::<Vec<String>>();
+            for (i, line) in lines.iter().take(max_lines).enumerate() {
+                let text = if i + 1 == max_lines && lines.len() > max_lines {
+                    "…".to_string()
+                } else {
+                    fit_text(line, cell_width - 2.0, FONT_SIZE)
+                };
+                layer.use_text(
+                    text,
+                    FONT_SIZE,
+                    Mm(x + 1.0),
+                    Mm(y - LINE_HEIGHT * (i + 1) as f32),
+                    font,
+                );
+            }
+        }
+    }
+}
+
+fn draw_pdf_appendix(doc: &PdfDocumentReference, rows: &[ViolationRow], font: &IndirectFontRef) {
+    let title = "付録: 制約違反";
+    let headers = ["授業", "コマ", "教室", "種類", "詳細"];
+    let widths = [60.0, 30.0, 40.0, 30.0, PAGE_WIDTH - MARGIN * 2.0 - 160.0];
+    let row_height = 5.0;
+    let top = PAGE_HEIGHT - MARGIN - TITLE_HEIGHT;
+    let rows_per_page = ((top - MARGIN) / row_height).floor() as usize - 1;
+    let mut layer = add_page(doc, title, font);
+    if rows.is_empty() {
+        layer.use_text("制約違反はありません", 10.0, Mm(MARGIN), Mm(top - 5.0), font);
+        return;
+    }
+    for (page, chunk) in rows.chunks(rows_per_page.max(1)).enumerate() {
+        if page > 0 {
+            layer = add_page(doc, title, font);
+        }
+        let mut y = top;
+        let mut x = MARGIN;
+        for (header, width) in headers.iter().zip(widths.iter()) {
+            fill_rect(&layer, x, y, *width, row_height, Rgb::new(0.93, 0.93, 0.93, None));
+            draw_rect(&layer, x, y, *width, row_height);
+            layer.use_text(*header, 8.0, Mm(x + 1.0), Mm(y - 3.7), font);
+            x += width;
+        }
+        for row in chunk.iter() {
+            y -= row_height;
+            let values = [
+                &row.class_name,
+                &row.period_label,
+                &row.room_name,
+                &row.kind_label,
+                &row.detail,
+            ];
+            let mut x = MARGIN;
+            for (value, width) in values.iter().zip(widths.iter()) {
+                draw_rect(&layer, x, y, *width, row_height);
+                layer.use_text(
+                    fit_text(value, width - 2.0, 8.0),
+                    8.0,
+                    Mm(x + 1.0),
+                    Mm(y - 3.7),
+                    font,
+                );
+                x += width;
+            }
+        }
+    }
+}
+
+//日本語を出すために埋め込むフォント(.ttf/.otf)を指定する
+pub fn export_pdf_report(
+    timetable: &TimeTable,
+    input: &Input,
+    parameters: &AcoParameters,
+    font_path: &Path,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let pages = create_report_pages(timetable, input, parameters)?;
+    let rows = create_violation_rows(timetable, input, &get_period_labels(parameters));
+    let (doc, first_page, first_layer) = PdfDocument::new("時間割", Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let font = doc.add_external_font(File::open(font_path)?)?;
+    //PdfDocument::newで作られた最初のページは表紙として使う
+    let cover = doc.get_page(first_page).get_layer(first_layer);
+    cover.use_text("時間割", 24.0, Mm(MARGIN), Mm(PAGE_HEIGHT - MARGIN - 10.0), &font);
+    cover.use_text(
+        format!("{}ページ / 制約違反{}件", pages.len(), rows.len()),
+        12.0,
+        Mm(MARGIN),
+        Mm(PAGE_HEIGHT - MARGIN - 20.0),
+        &font,
+    );
+    for page in pages.iter() {
+        draw_pdf_page(&doc, page, &font);
+    }
+    draw_pdf_appendix(&doc, &rows, &font);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    doc.save(&mut BufWriter::new(File::create(path)?))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn handle_export_html_report(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<(), CommandError> {
+    info!("called handle_export_html_report {}", path);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    let html = create_html_report(timetable, &solver.input, &solver.parameters)?;
+    let path = Path::new(&path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(path)?;
+    file.write_all(html.as_bytes()).map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub fn handle_export_pdf_report(
+    session_manager: tauri::State<'_, SessionManager>,
+    font_path: String,
+    path: String,
+) -> Result<(), CommandError> {
+    info!("called handle_export_pdf_report {} {}", font_path, path);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    export_pdf_report(
+        timetable,
+        &solver.input,
+        &solver.parameters,
+        Path::new(&font_path),
+        Path::new(&path),
+    )
+    .map_err(CommandError::from)
+}
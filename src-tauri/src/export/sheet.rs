@@ -0,0 +1,213 @@
+use super::{get_export_path, sanitize_file_name};
+use crate::algorithm::aco::aco_parameters::{get_period_labels, AcoParameters};
+use crate::algorithm::time_table::projection::{
+    project_all_timetables, ProjectedEntry, ProjectedTimeTable, ViewKind,
+};
+use crate::algorithm::time_table::TimeTable;
+use crate::input::{get_room_labels, Input};
+use log::info;
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+//表形式で書き出す1枚分のデータ、rows[0]は見出し
+pub struct Sheet {
+    pub kind: Option<ViewKind>,
+    //kindがあるときは教員・学生グループのindex
+    pub index: usize,
+    pub name: String,
+    pub rows: Vec<Vec<String>>,
+}
+
+static GRID_SHEET_NAME: &str = "時間割";
+//Excelのシート名の上限
+static MAX_SHEET_NAME_LENGTH: usize = 31;
+
+//教室×コマの表、行がコマで列が教室
+pub fn create_grid_sheet(timetable: &TimeTable, input: &Input, period_labels: &[String]) -> Sheet {
+    let mut header = vec!["".to_string()];
+    header.extend(get_room_labels(input));
+    let mut grid = vec![vec![Vec::<String>::new(); timetable.room_size]; timetable.period_size];
+    for cell in timetable.class_list.iter().flatten() {
+        let class = &input.get_classes()[cell.class_index];
+        for i in 0..class.serial_size {
+            let period = cell.period + i;
+            if period >= timetable.period_size {
+                break;
+            }
+            grid[period][cell.room].push(class.name.clone());
+        }
+    }
+    let mut rows = vec![header];
+    for (period, row) in grid.iter().enumerate() {
+        let mut line = vec![period_labels.get(period).cloned().unwrap_or_default()];
+        line.extend(row.iter().map(|x| x.join(" / ")));
+        rows.push(line);
+    }
+    Sheet {
+        kind: None,
+        index: 0,
+        name: GRID_SHEET_NAME.to_string(),
+        rows,
+    }
+}
+
+fn format_entry(entry: &ProjectedEntry, kind: ViewKind) -> String {
+    match kind {
+        ViewKind::Room => format!("{} [{}]", entry.class_name, entry.teachers.join(", ")),
+        ViewKind::Teacher | ViewKind::StudentGroup => {
+            format!("{} ({})", entry.class_name, entry.room_name)
+        }
+    }
+}
+
+//教員・学生グループごとの表、行がコマ
+pub fn create_projection_sheet(projected: &ProjectedTimeTable, period_labels: &[String]) -> Sheet {
+    let mut rows = vec![vec!["".to_string(), projected.name.clone()]];
+    for (day, periods) in projected.slots.iter().enumerate() {
+        for (period_in_day, entries) in periods.iter().enumerate() {
+            let period = day * projected.num_of_day_lengths + period_in_day;
+            if period >= period_labels.len() {
+                break;
+            }
+            rows.push(vec![
+                period_labels[period].clone(),
+                entries
+                    .iter()
+                    .map(|x| format_entry(x, projected.kind))
+                    .collect::<Vec<String>>()
+                    .join(" / "),
+            ]);
+        }
+    }
+    Sheet {
+        kind: Some(projected.kind),
+        index: projected.index,
+        name: projected.name.clone(),
+        rows,
+    }
+}
+
+pub fn create_sheets(
+    timetable: &TimeTable,
+    input: &Input,
+    parameters: &AcoParameters,
+) -> Result<Vec<Sheet>, CommandError> {
+    let period_labels = get_period_labels(parameters);
+    let mut sheets = vec![create_grid_sheet(timetable, input, &period_labels)];
+    for kind in [ViewKind::Teacher, ViewKind::StudentGroup] {
+        for projected in
+            project_all_timetables(timetable, input, parameters.num_of_day_lengths, kind)?
+        {
+            sheets.push(create_projection_sheet(&projected, &period_labels));
+        }
+    }
+    Ok(sheets)
+}
+
+//シートごとに1つのcsvを書き出す
+pub fn export_csv(sheets: &[Sheet], dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    std::fs::create_dir_all(dir)?;
+    let mut res = Vec::new();
+    for sheet in sheets.iter() {
+        let path = match sheet.kind {
+            Some(kind) => get_export_path(dir, kind, sheet.index, &sheet.name, "csv"),
+            None => dir.join(format!("{}.csv", sanitize_file_name(&sheet.name))),
+        };
+        let mut writer = csv::Writer::from_path(&path)?;
+        for row in sheet.rows.iter() {
+            writer.write_record(row)?;
+        }
+        writer.flush()?;
+        res.push(path);
+    }
+    Ok(res)
+}
+
+// sheet names must be unique, at most 31 chars and must not contain []:*?/\
+fn get_sheet_name(sheet: &Sheet, used: &mut HashSet<String>) -> String {
+    let prefix = match sheet.kind {
+        Some(ViewKind::Teacher) => "教員_",
+        Some(ViewKind::StudentGroup) => "学生_",
+        Some(ViewKind::Room) => "教室_",
+        None => "",
+    };
+    let base = format!("{}{}", prefix, sheet.name)
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .take(MAX_SHEET_NAME_LENGTH)
+        .collect::<String>();
+    let mut name = base.clone();
+    let mut cnt = 1;
+    while used.contains(&name.to_lowercase()) {
+        let suffix = format!("~{}", cnt);
+        name = base
+            .chars()
+            .take(MAX_SHEET_NAME_LENGTH - suffix.chars().count())
+            .collect::<String>()
+            + &suffix;
+        cnt += 1;
+    }
+    used.insert(name.to_lowercase());
+    name
+}
+
+//1つのブックにシートごとのワークシートを書き出す
+pub fn export_xlsx(sheets: &[Sheet], path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut workbook = Workbook::new();
+    let header_format = Format::new().set_bold();
+    let mut used = HashSet::new();
+    for sheet in sheets.iter() {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(get_sheet_name(sheet, &mut used))?;
+        for (i, row) in sheet.rows.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                if i == 0 || j == 0 {
+                    worksheet.write_string_with_format(i as u32, j as u16, value, &header_format)?;
+                } else {
+                    worksheet.write_string(i as u32, j as u16, value)?;
+                }
+            }
+        }
+        worksheet.set_column_width(0, 14)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    workbook.save(path)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn handle_export_csv(
+    session_manager: tauri::State<'_, SessionManager>,
+    dir: String,
+) -> Result<Vec<String>, CommandError> {
+    info!("called handle_export_csv {}", dir);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    let sheets = create_sheets(timetable, &solver.input, &solver.parameters)?;
+    let paths = export_csv(&sheets, Path::new(&dir))?;
+    Ok(paths
+        .iter()
+        .map(|x| x.to_string_lossy().to_string())
+        .collect())
+}
+
+#[tauri::command]
+pub fn handle_export_xlsx(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<(), CommandError> {
+    info!("called handle_export_xlsx {}", path);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    let sheets = create_sheets(timetable, &solver.input, &solver.parameters)?;
+    export_xlsx(&sheets, Path::new(&path)).map_err(CommandError::from)
+}
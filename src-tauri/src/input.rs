@@ -1,12 +1,17 @@
 use serde::{Deserialize, Serialize};
 
 use self::{student_group::StudentGroup, teacher::Teacher};
-use std::sync::Mutex;
+use std::path::Path;
 use std::{error::Error, vec};
 use log::info;
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
 
 pub mod class;
 mod column;
+pub mod fet;
+pub mod input_file;
+pub mod itc2007;
 pub mod room;
 mod student_group;
 pub mod teacher;
@@ -19,30 +24,88 @@ pub struct Input {
     teachers: Vec<teacher::Teacher>,
 }
 
-const TEACHERS_CSV_PATH: &str = "./csvdata/teachers.csv";
-const STUDENT_GROUPS_CSV_PATH: &str = "./csvdata/student_groups.csv";
-const CLASSES_CSV_PATH: &str = "./csvdata/classes.csv";
-const ROOMS_CSV_PATH: &str = "./csvdata/rooms.csv";
+pub const CSV_DIR: &str = "./csvdata";
+const TEACHERS_CSV_NAME: &str = "teachers.csv";
+const STUDENT_GROUPS_CSV_NAME: &str = "student_groups.csv";
+const CLASSES_CSV_NAME: &str = "classes.csv";
+const ROOMS_CSV_NAME: &str = "rooms.csv";
 
 impl Input {
-    pub fn new() -> Input {
-        let teachers = Input::read_teachers_from_csv(&TEACHERS_CSV_PATH.to_string()).unwrap();
-        let rooms = Input::read_rooms_from_csv(&ROOMS_CSV_PATH.to_string()).unwrap();
+    pub fn read_from_csv_dir(dir: &Path) -> Result<Input, Box<dyn Error>> {
+        let teachers = Input::read_teachers_from_csv(&path_to_string(dir, TEACHERS_CSV_NAME))?;
+        let rooms = Input::read_rooms_from_csv(&path_to_string(dir, ROOMS_CSV_NAME))?;
         let student_groups =
-            Input::read_student_groups_from_csv(&STUDENT_GROUPS_CSV_PATH.to_string()).unwrap();
+            Input::read_student_groups_from_csv(&path_to_string(dir, STUDENT_GROUPS_CSV_NAME))?;
         let classes = Input::read_classes_from_csv(
-            &CLASSES_CSV_PATH.to_string(),
+            &path_to_string(dir, CLASSES_CSV_NAME),
             &teachers,
             &rooms,
             &student_groups,
-        )
-        .unwrap();
-        Input {
+        )?;
+        Ok(Input {
             classes,
             rooms,
             student_groups,
             teachers,
+        })
+    }
+
+    //read_from_csv_dirで読める形で書き出す、名前のリストは","区切り
+    pub fn write_to_csv_dir(&self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+        let mut writer = csv::Writer::from_path(dir.join(TEACHERS_CSV_NAME))?;
+        writer.write_record(["id", "name", "absent"])?;
+        for teacher in self.teachers.iter() {
+            writer.write_record([
+                teacher.id.to_string(),
+                teacher.name.clone(),
+                join_numbers(&teacher.absent_days),
+            ])?;
+        }
+        writer.flush()?;
+        let mut writer = csv::Writer::from_path(dir.join(ROOMS_CSV_NAME))?;
+        writer.write_record(["id", "name", "capacity"])?;
+        for room in self.rooms.iter() {
+            writer.write_record([
+                room.id.to_string(),
+                room.name.clone(),
+                room.capacity.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        let mut writer = csv::Writer::from_path(dir.join(STUDENT_GROUPS_CSV_NAME))?;
+        writer.write_record(["id", "name"])?;
+        for student_group in self.student_groups.iter() {
+            writer.write_record([student_group.id.to_string(), student_group.name.clone()])?;
+        }
+        writer.flush()?;
+        let mut writer = csv::Writer::from_path(dir.join(CLASSES_CSV_NAME))?;
+        writer.write_record([
+            "id",
+            "name",
+            "teachers",
+            "rooms",
+            "groups",
+            "students",
+            "serial",
+            "unavailable",
+        ])?;
+        for class in self.classes.iter() {
+            writer.write_record([
+                class.id.to_string(),
+                class.name.clone(),
+                join_names(&class.teacher_indexes, |x| &self.teachers[x].name),
+                join_names(&class.room_candidates_indexes, |x| &self.rooms[x].name),
+                join_names(&class.students_group_indexes, |x| {
+                    &self.student_groups[x].name
+                }),
+                class.num_of_students.to_string(),
+                class.serial_size.to_string(),
+                join_numbers(&class.unavailable_periods),
+            ])?;
         }
+        writer.flush()?;
+        Ok(())
     }
 
     fn read_teachers_from_csv(file_path: &String) -> Result<Vec<teacher::Teacher>, Box<dyn Error>> {
@@ -52,15 +115,15 @@ impl Input {
         let mut teachers = Vec::new();
         for (index, result) in rdr.records().enumerate() {
             let record = result?;
-            let id = record[0].parse::<usize>().unwrap();
+            let id = record[0].parse::<usize>()?;
             let name = record[1].to_string();
             let absent_days = if record[2].is_empty() {
                 vec![]
             } else {
                 record[2]
                     .split(",")
-                    .map(|x| x.parse::<usize>().unwrap())
-                    .collect()
+                    .map(|x| x.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()?
             };
             let index = index as usize;
             teachers.push(teacher::Teacher {
@@ -82,9 +145,9 @@ impl Input {
         for (index, result) in rdr.records().enumerate() {
             let record = result?;
             let index = index as usize;
-            let id = record[0].parse::<usize>().unwrap();
+            let id = record[0].parse::<usize>()?;
             let name = record[1].to_string();
-            let capacity = record[2].parse::<usize>().unwrap();
+            let capacity = record[2].parse::<usize>()?;
             rooms.push(room::Room {
                 id,
                 index,
@@ -104,7 +167,7 @@ impl Input {
         let mut student_groups = Vec::new();
         for (index, result) in rdr.records().enumerate() {
             let record = result?;
-            let id = record[0].parse::<usize>().unwrap();
+            let id = record[0].parse::<usize>()?;
             let name = record[1].to_string();
             let index = index as usize;
             student_groups.push(student_group::StudentGroup { id, index, name });
@@ -126,7 +189,7 @@ impl Input {
         for (index, result) in rdr.records().enumerate() {
             let record = result?;
             let index = index as usize;
-            let id = record[0].parse::<usize>().unwrap();
+            let id = record[0].parse::<usize>()?;
             let name = record[1].to_string();
             let mut teacher_indexes = Vec::new();
             for i in record[2].split(",") {
@@ -136,27 +199,41 @@ impl Input {
                 if let Some(add) = teachers.iter().position(|x| x.name == i) {
                     teacher_indexes.push(add as usize);
                 } else {
-                    panic!("teacher not found {}", i);
+                    return Err(format!("teacher not found: {}", i).into());
                 }
             }
             let mut room_candidates_indexes = Vec::new();
             for i in record[3].split(",") {
+                if i == "" {
+                    continue;
+                }
                 if let Some(add) = rooms.iter().position(|x| x.name == i) {
                     room_candidates_indexes.push(add as usize);
                 } else {
-                    panic!("room not found");
+                    return Err(format!("room not found: {}", i).into());
                 }
             }
             let mut students_group_indexes = Vec::new();
             for i in record[4].split(",") {
+                if i == "" {
+                    continue;
+                }
                 if let Some(add) = student_groups.iter().position(|x| x.name == i) {
                     students_group_indexes.push(add as usize);
                 } else {
-                    panic!("student_group not found: {}", i);
+                    return Err(format!("student_group not found: {}", i).into());
                 }
             }
-            let num_of_students = record[5].parse::<usize>().unwrap();
-            let serial_size = record[6].parse::<usize>().unwrap();
+            let num_of_students = record[5].parse::<usize>()?;
+            let serial_size = record[6].parse::<usize>()?;
+            //8列目は省略できる
+            let unavailable_periods = match record.get(7) {
+                Some(x) if !x.is_empty() => x
+                    .split(",")
+                    .map(|x| x.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()?,
+                _ => Vec::new(),
+            };
             classes.push(class::Class {
                 id,
                 index,
@@ -166,6 +243,7 @@ impl Input {
                 room_candidates_indexes,
                 students_group_indexes,
                 serial_size,
+                unavailable_periods,
             });
         }
         Ok(classes)
@@ -185,28 +263,44 @@ impl Input {
     }
 }
 
-pub struct InputManager {
-    pub input: Mutex<Option<Input>>,
+fn path_to_string(dir: &Path, name: &str) -> String {
+    dir.join(name).to_string_lossy().to_string()
+}
+
+fn join_numbers(values: &[usize]) -> String {
+    values
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn join_names<'a, F: Fn(usize) -> &'a String>(indexes: &[usize], get_name: F) -> String {
+    indexes
+        .iter()
+        .map(|&x| get_name(x).clone())
+        .collect::<Vec<String>>()
+        .join(",")
 }
 
 #[tauri::command]
-pub fn handle_set_input(input_manager: tauri::State<'_, InputManager>) -> Result<(), String> {
+pub fn handle_set_input(session_manager: tauri::State<'_, SessionManager>) -> Result<(), CommandError> {
     info!("called handle_set_input");
-    let input = Input::new();
-    let mut managed_input = input_manager.input.lock().unwrap();
-    *managed_input = Some(input);
+    let input = Input::read_from_csv_dir(Path::new(CSV_DIR))?;
+    session_manager.session.lock_state()?.load_input(input);
     Ok(())
 }
 
+pub fn get_room_labels(input: &Input) -> Vec<String> {
+    input.get_rooms().iter().map(|x| x.name.clone()
+        + " (収容人数:" + &x.capacity.to_string() + ")").collect()
+}
+
 #[tauri::command]
 pub fn handle_get_rooms(
-    input_manager: tauri::State<'_, InputManager>,
-) -> Result<Vec<String>, String> {
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<Vec<String>, CommandError> {
     info!("called handle_get_rooms");
-    let input = input_manager.input.lock().unwrap();
-    if let Some(input) = input.as_ref() {
-        return Ok(input.get_rooms().iter().map(|x| x.name.clone()
-            + " (収容人数:" + &x.capacity.to_string() + ")").collect());
-    }
-    return Err("no input".to_string());
+    let session = session_manager.session.lock_state()?;
+    return Ok(get_room_labels(session.get_input()?));
 }
@@ -11,6 +11,9 @@ pub struct Class {
     pub room_candidates_indexes: Vec<usize>,
     pub students_group_indexes: Vec<usize>,
     pub serial_size: usize,
+    //教員の都合とは別に、この授業を置けないコマ
+    #[serde(default)]
+    pub unavailable_periods: Vec<usize>,
 }
 
 impl Class {
@@ -0,0 +1,728 @@
+// FETの.fet形式 (XML)
+// Students_Listは最下層のsubgroupをstudent groupとして展開し、year/groupはその集合として扱う
+// 重み100%の時間・教室の制約だけを読み込み、それ以外は警告を出して読み捨てる
+use super::{class, room, student_group, teacher, Input};
+use crate::algorithm::aco::aco_parameters::get_day_label;
+use crate::algorithm::aco::graph::LockKind;
+use crate::algorithm::time_table::cell::ActiveCell;
+use crate::algorithm::time_table::TimeTable;
+use log::{info, warn};
+use roxmltree::{Document, Node};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+pub struct FetInstance {
+    pub name: String,
+    pub num_of_days: usize,
+    pub hours_per_day: usize,
+    pub input: Input,
+    pub warnings: Vec<String>,
+}
+
+impl FetInstance {
+    pub fn get_num_of_periods(&self) -> usize {
+        self.num_of_days * self.hours_per_day
+    }
+}
+
+fn get_children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |x| x.has_tag_name(name))
+}
+
+fn get_child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|x| x.has_tag_name(name))
+}
+
+fn get_text(node: Node, name: &str) -> Option<String> {
+    get_child(node, name).map(|x| x.text().unwrap_or("").trim().to_string())
+}
+
+fn get_texts(node: Node, name: &str) -> Vec<String> {
+    get_children(node, name)
+        .map(|x| x.text().unwrap_or("").trim().to_string())
+        .collect()
+}
+
+fn get_required_text(node: Node, name: &str) -> Result<String, String> {
+    get_text(node, name).ok_or(format!(
+        "{}: missing {}",
+        node.tag_name().name(),
+        name
+    ))
+}
+
+fn get_number<T: std::str::FromStr + Default>(node: Node, name: &str) -> Result<T, String> {
+    match get_text(node, name) {
+        Some(value) if !value.is_empty() => value
+            .parse::<T>()
+            .map_err(|_| format!("{}: invalid {}: {}", node.tag_name().name(), name, value)),
+        _ => Ok(T::default()),
+    }
+}
+
+fn is_active(node: Node) -> bool {
+    get_text(node, "Active").map(|x| x != "false").unwrap_or(true)
+}
+
+fn get_names(list: Option<Node>, item: &str) -> Vec<String> {
+    match list {
+        Some(list) => get_children(list, item)
+            .filter_map(|x| get_text(x, "Name"))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+//years/groups/subgroupsを再帰的に辿り、各students setが含む最下層のgroupを求める
+fn read_students_set(
+    node: Node,
+    student_groups: &mut Vec<student_group::StudentGroup>,
+    sets: &mut HashMap<String, (BTreeSet<usize>, usize)>,
+) -> Result<BTreeSet<usize>, String> {
+    let name = get_required_text(node, "Name")?;
+    let num_of_students = get_number::<usize>(node, "Number_of_Students")?;
+    let mut leaves = BTreeSet::new();
+    for child in node
+        .children()
+        .filter(|x| x.has_tag_name("Group") || x.has_tag_name("Subgroup"))
+    {
+        leaves.extend(read_students_set(child, student_groups, sets)?);
+    }
+    if leaves.is_empty() {
+        if let Some((set, _)) = sets.get(&name) {
+            leaves.extend(set.iter());
+        } else {
+            let index = student_groups.len();
+            student_groups.push(student_group::StudentGroup {
+                id: index,
+                name: name.clone(),
+                index,
+            });
+            leaves.insert(index);
+        }
+    }
+    sets.insert(name, (leaves.clone(), num_of_students));
+    Ok(leaves)
+}
+
+struct PeriodNames<'a> {
+    day_names: &'a [String],
+    hour_names: &'a [String],
+}
+
+impl<'a> PeriodNames<'a> {
+    fn get_period(&self, day: &str, hour: &str) -> Result<usize, String> {
+        let day_index = self
+            .day_names
+            .iter()
+            .position(|x| x == day)
+            .ok_or(format!("unknown day: {}", day))?;
+        let hour_index = self
+            .hour_names
+            .iter()
+            .position(|x| x == hour)
+            .ok_or(format!("unknown hour: {}", hour))?;
+        Ok(day_index * self.hour_names.len() + hour_index)
+    }
+
+    fn get_periods(&self, node: Node, item: &str, day: &str, hour: &str) -> Result<Vec<usize>, String> {
+        let mut res = Vec::new();
+        for time in get_children(node, item) {
+            res.push(self.get_period(
+                &get_required_text(time, day)?,
+                &get_required_text(time, hour)?,
+            )?);
+        }
+        Ok(res)
+    }
+}
+
+pub fn parse_fet(text: &str) -> Result<FetInstance, Box<dyn Error>> {
+    let doc = Document::parse(text)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("fet") {
+        return Err("not a fet file".into());
+    }
+    let mut warnings = Vec::<String>::new();
+    let day_names = get_names(get_child(root, "Days_List"), "Day");
+    let hour_names = get_names(get_child(root, "Hours_List"), "Hour");
+    if day_names.is_empty() || hour_names.is_empty() {
+        return Err("no days or hours".into());
+    }
+    let period_names = PeriodNames {
+        day_names: &day_names,
+        hour_names: &hour_names,
+    };
+    let num_of_periods = day_names.len() * hour_names.len();
+
+    let mut teachers = get_names(get_child(root, "Teachers_List"), "Teacher")
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| teacher::Teacher {
+            id: index,
+            index,
+            name,
+            absent_days: Vec::new(),
+        })
+        .collect::<Vec<teacher::Teacher>>();
+
+    let mut student_groups = Vec::<student_group::StudentGroup>::new();
+    let mut sets = HashMap::<String, (BTreeSet<usize>, usize)>::new();
+    if let Some(list) = get_child(root, "Students_List") {
+        for year in get_children(list, "Year") {
+            read_students_set(year, &mut student_groups, &mut sets)?;
+        }
+    }
+
+    let mut rooms = Vec::<room::Room>::new();
+    if let Some(list) = get_child(root, "Rooms_List") {
+        for node in get_children(list, "Room") {
+            if get_text(node, "Virtual").as_deref() == Some("true") {
+                warnings.push(format!(
+                    "virtual room is treated as a real room: {}",
+                    get_text(node, "Name").unwrap_or_default()
+                ));
+            }
+            rooms.push(room::Room {
+                id: rooms.len(),
+                index: rooms.len(),
+                name: get_required_text(node, "Name")?,
+                capacity: get_number(node, "Capacity")?,
+            });
+        }
+    }
+    if rooms.is_empty() {
+        return Err("no rooms".into());
+    }
+
+    let mut classes = Vec::<class::Class>::new();
+    let mut subjects = Vec::<String>::new();
+    if let Some(list) = get_child(root, "Activities_List") {
+        for node in get_children(list, "Activity") {
+            let id = get_number::<usize>(node, "Id")?;
+            if !is_active(node) {
+                warnings.push(format!("inactive activity is skipped: {}", id));
+                continue;
+            }
+            let mut teacher_indexes = Vec::new();
+            for name in get_texts(node, "Teacher") {
+                let index = teachers
+                    .iter()
+                    .position(|x| x.name == name)
+                    .ok_or(format!("activity {}: unknown teacher: {}", id, name))?;
+                teacher_indexes.push(index);
+            }
+            let mut students_group_indexes = BTreeSet::new();
+            let mut num_of_students = 0;
+            for name in get_texts(node, "Students") {
+                let (leaves, size) = sets
+                    .get(&name)
+                    .ok_or(format!("activity {}: unknown students: {}", id, name))?;
+                students_group_indexes.extend(leaves.iter());
+                num_of_students += size;
+            }
+            if let Some(size) = get_text(node, "Number_Of_Students") {
+                num_of_students = size
+                    .parse()
+                    .map_err(|_| format!("activity {}: invalid number of students", id))?;
+            }
+            let subject = get_text(node, "Subject").unwrap_or_default();
+            classes.push(class::Class {
+                id,
+                index: classes.len(),
+                num_of_students,
+                name: subject.clone(),
+                teacher_indexes,
+                room_candidates_indexes: (0..rooms.len()).collect(),
+                students_group_indexes: students_group_indexes.into_iter().collect(),
+                serial_size: get_number::<usize>(node, "Duration")?.max(1),
+                unavailable_periods: Vec::new(),
+            });
+            subjects.push(subject);
+        }
+    }
+
+    let find_class = |node: Node| -> Result<usize, String> {
+        let id = get_number::<usize>(node, "Activity_Id")?;
+        classes
+            .iter()
+            .position(|x| x.id == id)
+            .ok_or(format!("unknown activity: {}", id))
+    };
+    let find_rooms = |names: Vec<String>| -> Result<Vec<usize>, String> {
+        names
+            .iter()
+            .map(|name| {
+                rooms
+                    .iter()
+                    .position(|x| &x.name == name)
+                    .ok_or(format!("unknown room: {}", name))
+            })
+            .collect()
+    };
+
+    let mut unavailable_periods = vec![BTreeSet::<usize>::new(); classes.len()];
+    let mut room_candidates = vec![None::<Vec<usize>>; classes.len()];
+    let constraints = ["Time_Constraints_List", "Space_Constraints_List"]
+        .iter()
+        .filter_map(|x| get_child(root, x))
+        .flat_map(|x| x.children().filter(|x| x.is_element()))
+        .collect::<Vec<Node>>();
+    for node in constraints {
+        let tag = node.tag_name().name();
+        if !is_active(node) {
+            continue;
+        }
+        let weight = get_number::<f64>(node, "Weight_Percentage")?;
+        if weight < 100.0 {
+            warnings.push(format!("soft constraint is ignored: {} ({}%)", tag, weight));
+            continue;
+        }
+        match tag {
+            "ConstraintBasicCompulsoryTime" | "ConstraintBasicCompulsorySpace" => {}
+            "ConstraintTeacherNotAvailableTimes" => {
+                let name = get_required_text(node, "Teacher")?;
+                let periods = period_names.get_periods(node, "Not_Available_Time", "Day", "Hour")?;
+                let teacher = teachers
+                    .iter_mut()
+                    .find(|x| x.name == name)
+                    .ok_or(format!("{}: unknown teacher: {}", tag, name))?;
+                teacher.absent_days.extend(periods);
+            }
+            "ConstraintStudentsSetNotAvailableTimes" => {
+                let name = get_required_text(node, "Students")?;
+                let (leaves, _) = sets
+                    .get(&name)
+                    .ok_or(format!("{}: unknown students: {}", tag, name))?;
+                let periods = period_names.get_periods(node, "Not_Available_Time", "Day", "Hour")?;
+                for (i, class) in classes.iter().enumerate() {
+                    if class.students_group_indexes.iter().any(|x| leaves.contains(x)) {
+                        unavailable_periods[i].extend(periods.iter());
+                    }
+                }
+            }
+            "ConstraintBreakTimes" => {
+                let periods = period_names.get_periods(node, "Break_Time", "Day", "Hour")?;
+                for set in unavailable_periods.iter_mut() {
+                    set.extend(periods.iter());
+                }
+            }
+            "ConstraintActivityPreferredStartingTime" => {
+                let class = find_class(node)?;
+                let period = period_names.get_period(
+                    &get_required_text(node, "Preferred_Day")?,
+                    &get_required_text(node, "Preferred_Hour")?,
+                )?;
+                unavailable_periods[class].extend((0..num_of_periods).filter(|&x| x != period));
+            }
+            "ConstraintActivityPreferredStartingTimes" => {
+                let class = find_class(node)?;
+                let periods = period_names.get_periods(
+                    node,
+                    "Preferred_Starting_Time",
+                    "Preferred_Starting_Day",
+                    "Preferred_Starting_Hour",
+                )?;
+                unavailable_periods[class]
+                    .extend((0..num_of_periods).filter(|x| !periods.contains(x)));
+            }
+            "ConstraintActivityPreferredRoom" => {
+                let class = find_class(node)?;
+                room_candidates[class] = Some(find_rooms(vec![get_required_text(node, "Room")?])?);
+            }
+            "ConstraintActivityPreferredRooms" => {
+                let class = find_class(node)?;
+                room_candidates[class] = Some(find_rooms(get_texts(node, "Preferred_Room"))?);
+            }
+            "ConstraintSubjectPreferredRoom" | "ConstraintSubjectPreferredRooms" => {
+                let subject = get_required_text(node, "Subject")?;
+                let names = if tag == "ConstraintSubjectPreferredRoom" {
+                    vec![get_required_text(node, "Room")?]
+                } else {
+                    get_texts(node, "Preferred_Room")
+                };
+                let candidates = find_rooms(names)?;
+                for (i, x) in subjects.iter().enumerate() {
+                    //activity単位の指定を優先する
+                    if x == &subject && room_candidates[i].is_none() {
+                        room_candidates[i] = Some(candidates.clone());
+                    }
+                }
+            }
+            _ => {
+                warnings.push(format!("unsupported constraint is ignored: {}", tag));
+            }
+        }
+    }
+    for (i, class) in classes.iter_mut().enumerate() {
+        class.unavailable_periods = unavailable_periods[i].iter().cloned().collect();
+        if let Some(candidates) = room_candidates[i].take() {
+            if candidates.is_empty() {
+                return Err(format!("activity {} has no room candidates", class.id).into());
+            }
+            class.room_candidates_indexes = candidates;
+        }
+    }
+    for teacher in teachers.iter_mut() {
+        teacher.absent_days.sort();
+        teacher.absent_days.dedup();
+    }
+    for warning in warnings.iter() {
+        warn!("{}", warning);
+    }
+
+    Ok(FetInstance {
+        name: get_text(root, "Institution_Name").unwrap_or_default(),
+        num_of_days: day_names.len(),
+        hours_per_day: hour_names.len(),
+        input: Input {
+            classes,
+            rooms,
+            student_groups,
+            teachers,
+        },
+        warnings,
+    })
+}
+
+pub fn read_fet(path: &str) -> Result<FetInstance, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)?;
+    parse_fet(&text)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn get_hour_label(hour: usize) -> String {
+    format!("{}限", hour + 1)
+}
+
+//FETのactivity idは1始まり
+//読み込んだidが使えるならそのまま使い、読み込み→書き出しでidが変わらないようにする
+fn get_activity_ids(input: &Input) -> Vec<usize> {
+    let ids = input.get_classes().iter().map(|x| x.id).collect::<BTreeSet<usize>>();
+    if ids.len() == input.get_classes().len() && !ids.contains(&0) {
+        input.get_classes().iter().map(|x| x.id).collect()
+    } else {
+        input.get_classes().iter().map(|x| x.index + 1).collect()
+    }
+}
+
+//学生集団の人数は、その集団が受ける授業の人数を集団数で割った最大値とする
+fn get_num_of_students_each_group(input: &Input) -> Vec<usize> {
+    let mut res = vec![0; input.get_student_groups().len()];
+    for class in input.get_classes().iter() {
+        let num_of_groups = class.students_group_indexes.len();
+        if num_of_groups == 0 {
+            continue;
+        }
+        let size = class.num_of_students.div_ceil(num_of_groups);
+        for &group in class.students_group_indexes.iter() {
+            res[group] = res[group].max(size);
+        }
+    }
+    res
+}
+
+//連続授業のどこかが置けないコマにかかるか
+fn overlaps_periods(periods: &[usize], period: usize, serial_size: usize) -> bool {
+    (period..period + serial_size).any(|x| periods.contains(&x))
+}
+
+//置けないコマにかかる配置を重み100%で書き出すと、FETでは解けない矛盾した制約になる
+fn is_placement_available(class: &class::Class, input: &Input, period: usize) -> bool {
+    !overlaps_periods(&class.unavailable_periods, period, class.serial_size)
+        && class
+            .teacher_indexes
+            .iter()
+            .all(|&x| {
+                !overlaps_periods(&input.get_teachers()[x].absent_days, period, class.serial_size)
+            })
+}
+
+struct XmlWriter {
+    res: String,
+    depth: usize,
+}
+
+impl XmlWriter {
+    fn open(&mut self, tag: &str) {
+        self.res
+            .push_str(&format!("{}<{}>\n", "\t".repeat(self.depth), tag));
+        self.depth += 1;
+    }
+    fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.res
+            .push_str(&format!("{}</{}>\n", "\t".repeat(self.depth), tag));
+    }
+    fn text<T: ToString>(&mut self, tag: &str, value: T) {
+        self.res.push_str(&format!(
+            "{}<{}>{}</{}>\n",
+            "\t".repeat(self.depth),
+            tag,
+            escape_xml(&value.to_string()),
+            tag
+        ));
+    }
+    fn time(&mut self, tag: &str, day_tag: &str, hour_tag: &str, period: usize, hours_per_day: usize) {
+        self.open(tag);
+        self.text(day_tag, get_day_label(period / hours_per_day));
+        self.text(hour_tag, get_hour_label(period % hours_per_day));
+        self.close(tag);
+    }
+}
+
+//時間割の配置は教室・開始時刻を固定する制約として書き出す
+//部屋だけ固定された授業はコマを、コマだけ固定された授業は部屋を動かせる
+fn is_period_locked(cell: &ActiveCell) -> bool {
+    cell.get_lock()
+        .map(|x| x.kind != LockKind::RoomOnly)
+        .unwrap_or(false)
+}
+
+fn is_room_locked(cell: &ActiveCell) -> bool {
+    cell.get_lock()
+        .map(|x| x.kind != LockKind::PeriodOnly)
+        .unwrap_or(false)
+}
+
+pub fn create_fet(
+    timetable: &TimeTable,
+    input: &Input,
+    num_of_periods: usize,
+    hours_per_day: usize,
+) -> Result<String, Box<dyn Error>> {
+    if hours_per_day == 0 {
+        return Err("a day has no periods".into());
+    }
+    let num_of_days = num_of_periods.div_ceil(hours_per_day);
+    let activity_ids = get_activity_ids(input);
+    let mut w = XmlWriter {
+        res: "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string(),
+        depth: 0,
+    };
+    w.open("fet version=\"6.0.0\"");
+    w.text("Institution_Name", "ClassistAnt");
+    w.open("Days_List");
+    w.text("Number_of_Days", num_of_days);
+    for day in 0..num_of_days {
+        w.open("Day");
+        w.text("Name", get_day_label(day));
+        w.close("Day");
+    }
+    w.close("Days_List");
+    w.open("Hours_List");
+    w.text("Number_of_Hours", hours_per_day);
+    for hour in 0..hours_per_day {
+        w.open("Hour");
+        w.text("Name", get_hour_label(hour));
+        w.close("Hour");
+    }
+    w.close("Hours_List");
+
+    let mut subjects = input
+        .get_classes()
+        .iter()
+        .map(|x| x.name.clone())
+        .collect::<Vec<String>>();
+    subjects.sort();
+    subjects.dedup();
+    w.open("Subjects_List");
+    for subject in subjects.iter() {
+        w.open("Subject");
+        w.text("Name", subject);
+        w.close("Subject");
+    }
+    w.close("Subjects_List");
+    w.open("Teachers_List");
+    for teacher in input.get_teachers().iter() {
+        w.open("Teacher");
+        w.text("Name", &teacher.name);
+        w.close("Teacher");
+    }
+    w.close("Teachers_List");
+    let num_of_students_each_group = get_num_of_students_each_group(input);
+    w.open("Students_List");
+    for (index, student_group) in input.get_student_groups().iter().enumerate() {
+        w.open("Year");
+        w.text("Name", &student_group.name);
+        w.text("Number_of_Students", num_of_students_each_group[index]);
+        w.close("Year");
+    }
+    w.close("Students_List");
+
+    w.open("Activities_List");
+    for class in input.get_classes().iter() {
+        w.open("Activity");
+        for &teacher in class.teacher_indexes.iter() {
+            w.text("Teacher", &input.get_teachers()[teacher].name);
+        }
+        w.text("Subject", &class.name);
+        for &student_group in class.students_group_indexes.iter() {
+            w.text("Students", &input.get_student_groups()[student_group].name);
+        }
+        w.text("Duration", class.serial_size);
+        w.text("Total_Duration", class.serial_size);
+        w.text("Id", activity_ids[class.index]);
+        w.text("Activity_Group_Id", 0);
+        w.text("Number_Of_Students", class.num_of_students);
+        w.text("Active", "true");
+        w.close("Activity");
+    }
+    w.close("Activities_List");
+    w.open("Rooms_List");
+    for room in input.get_rooms().iter() {
+        w.open("Room");
+        w.text("Name", &room.name);
+        w.text("Capacity", room.capacity);
+        w.text("Virtual", "false");
+        w.close("Room");
+    }
+    w.close("Rooms_List");
+
+    w.open("Time_Constraints_List");
+    w.open("ConstraintBasicCompulsoryTime");
+    w.text("Weight_Percentage", 100);
+    w.text("Active", "true");
+    w.close("ConstraintBasicCompulsoryTime");
+    for teacher in input.get_teachers().iter() {
+        if teacher.absent_days.is_empty() {
+            continue;
+        }
+        w.open("ConstraintTeacherNotAvailableTimes");
+        w.text("Weight_Percentage", 100);
+        w.text("Teacher", &teacher.name);
+        w.text("Number_of_Not_Available_Times", teacher.absent_days.len());
+        for &period in teacher.absent_days.iter() {
+            w.time("Not_Available_Time", "Day", "Hour", period, hours_per_day);
+        }
+        w.text("Active", "true");
+        w.close("ConstraintTeacherNotAvailableTimes");
+    }
+    for class in input.get_classes().iter() {
+        if class.unavailable_periods.is_empty() {
+            continue;
+        }
+        let periods = (0..num_of_periods)
+            .filter(|&x| !overlaps_periods(&class.unavailable_periods, x, class.serial_size))
+            .collect::<Vec<usize>>();
+        w.open("ConstraintActivityPreferredStartingTimes");
+        w.text("Weight_Percentage", 100);
+        w.text("Activity_Id", activity_ids[class.index]);
+        w.text("Number_of_Preferred_Starting_Times", periods.len());
+        for period in periods {
+            w.time(
+                "Preferred_Starting_Time",
+                "Preferred_Starting_Day",
+                "Preferred_Starting_Hour",
+                period,
+                hours_per_day,
+            );
+        }
+        w.text("Active", "true");
+        w.close("ConstraintActivityPreferredStartingTimes");
+    }
+    for cell in timetable.class_list.iter().flatten() {
+        let class = &input.get_classes()[cell.class_index];
+        if !is_placement_available(class, input, cell.period) {
+            warn!(
+                "activity {}: placement on an unavailable period is not exported",
+                activity_ids[class.index]
+            );
+            continue;
+        }
+        w.open("ConstraintActivityPreferredStartingTime");
+        w.text("Weight_Percentage", 100);
+        w.text("Activity_Id", activity_ids[class.index]);
+        w.text("Preferred_Day", get_day_label(cell.period / hours_per_day));
+        w.text("Preferred_Hour", get_hour_label(cell.period % hours_per_day));
+        w.text("Permanently_Locked", is_period_locked(cell));
+        w.text("Active", "true");
+        w.close("ConstraintActivityPreferredStartingTime");
+    }
+    w.close("Time_Constraints_List");
+
+    w.open("Space_Constraints_List");
+    w.open("ConstraintBasicCompulsorySpace");
+    w.text("Weight_Percentage", 100);
+    w.text("Active", "true");
+    w.close("ConstraintBasicCompulsorySpace");
+    for class in input.get_classes().iter() {
+        if class.room_candidates_indexes.len() == input.get_rooms().len() {
+            continue;
+        }
+        w.open("ConstraintActivityPreferredRooms");
+        w.text("Weight_Percentage", 100);
+        w.text("Activity_Id", activity_ids[class.index]);
+        w.text("Number_of_Preferred_Rooms", class.room_candidates_indexes.len());
+        for &room in class.room_candidates_indexes.iter() {
+            w.text("Preferred_Room", &input.get_rooms()[room].name);
+        }
+        w.text("Active", "true");
+        w.close("ConstraintActivityPreferredRooms");
+    }
+    for cell in timetable.class_list.iter().flatten() {
+        let class = &input.get_classes()[cell.class_index];
+        if !class.room_candidates_indexes.contains(&cell.room) {
+            warn!(
+                "activity {}: placement in a room out of candidates is not exported",
+                activity_ids[class.index]
+            );
+            continue;
+        }
+        w.open("ConstraintActivityPreferredRoom");
+        w.text("Weight_Percentage", 100);
+        w.text("Activity_Id", activity_ids[class.index]);
+        w.text("Room", &input.get_rooms()[cell.room].name);
+        w.text("Permanently_Locked", is_room_locked(cell));
+        w.text("Active", "true");
+        w.close("ConstraintActivityPreferredRoom");
+    }
+    w.close("Space_Constraints_List");
+    w.close("fet");
+    Ok(w.res)
+}
+
+pub fn write_fet(
+    timetable: &TimeTable,
+    input: &Input,
+    num_of_periods: usize,
+    hours_per_day: usize,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let fet = create_fet(timetable, input, num_of_periods, hours_per_day)?;
+    let mut file = File::create(path)?;
+    file.write_all(fet.as_bytes())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn handle_export_fet(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<(), CommandError> {
+    info!("called handle_export_fet {}", path);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    write_fet(
+        timetable,
+        &solver.input,
+        solver.parameters.num_of_periods,
+        solver.parameters.num_of_day_lengths,
+        &path,
+    )
+    .map_err(CommandError::from)
+}
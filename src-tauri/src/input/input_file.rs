@@ -0,0 +1,336 @@
+// CSVの代わりに使えるJSON/TOML形式のinput
+// 教員・教室・学生グループは名前ではなくidで参照する
+use super::{class, room, student_group, teacher, Input};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+pub const INPUT_FILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TeacherEntry {
+    pub id: usize,
+    pub name: String,
+    //休みのコマ
+    #[serde(default)]
+    pub absent_periods: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomEntry {
+    pub id: usize,
+    pub name: String,
+    pub capacity: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StudentGroupEntry {
+    pub id: usize,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassEntry {
+    pub id: usize,
+    pub name: String,
+    #[serde(default)]
+    pub teachers: Vec<usize>,
+    pub rooms: Vec<usize>,
+    #[serde(default)]
+    pub student_groups: Vec<usize>,
+    pub num_of_students: usize,
+    #[serde(default = "default_serial_size")]
+    pub serial_size: usize,
+    #[serde(default)]
+    pub unavailable_periods: Vec<usize>,
+}
+
+fn default_serial_size() -> usize {
+    1
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InputFile {
+    pub version: u32,
+    pub teachers: Vec<TeacherEntry>,
+    pub rooms: Vec<RoomEntry>,
+    pub student_groups: Vec<StudentGroupEntry>,
+    pub classes: Vec<ClassEntry>,
+}
+
+//id -> index、重複したidはエラー
+fn index_ids<I: Iterator<Item = usize>>(ids: I, kind: &str) -> Result<HashMap<usize, usize>, String> {
+    let mut res = HashMap::new();
+    for (index, id) in ids.enumerate() {
+        if res.insert(id, index).is_some() {
+            return Err(format!("duplicate {} id: {}", kind, id));
+        }
+    }
+    Ok(res)
+}
+
+fn resolve_ids(
+    ids: &[usize],
+    indexes: &HashMap<usize, usize>,
+    kind: &str,
+    class: &ClassEntry,
+) -> Result<Vec<usize>, String> {
+    ids.iter()
+        .map(|id| {
+            indexes.get(id).cloned().ok_or(format!(
+                "class {} ({}): unknown {} id: {}",
+                class.id, class.name, kind, id
+            ))
+        })
+        .collect()
+}
+
+impl InputFile {
+    pub fn from_input(input: &Input) -> InputFile {
+        InputFile {
+            version: INPUT_FILE_VERSION,
+            teachers: input
+                .teachers
+                .iter()
+                .map(|x| TeacherEntry {
+                    id: x.id,
+                    name: x.name.clone(),
+                    absent_periods: x.absent_days.clone(),
+                })
+                .collect(),
+            rooms: input
+                .rooms
+                .iter()
+                .map(|x| RoomEntry {
+                    id: x.id,
+                    name: x.name.clone(),
+                    capacity: x.capacity,
+                })
+                .collect(),
+            student_groups: input
+                .student_groups
+                .iter()
+                .map(|x| StudentGroupEntry {
+                    id: x.id,
+                    name: x.name.clone(),
+                })
+                .collect(),
+            classes: input
+                .classes
+                .iter()
+                .map(|x| ClassEntry {
+                    id: x.id,
+                    name: x.name.clone(),
+                    teachers: x.teacher_indexes.iter().map(|&i| input.teachers[i].id).collect(),
+                    rooms: x
+                        .room_candidates_indexes
+                        .iter()
+                        .map(|&i| input.rooms[i].id)
+                        .collect(),
+                    student_groups: x
+                        .students_group_indexes
+                        .iter()
+                        .map(|&i| input.student_groups[i].id)
+                        .collect(),
+                    num_of_students: x.num_of_students,
+                    serial_size: x.serial_size,
+                    unavailable_periods: x.unavailable_periods.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn into_input(self) -> Result<Input, String> {
+        if self.version > INPUT_FILE_VERSION {
+            return Err(format!("unsupported input file version: {}", self.version));
+        }
+        let teacher_indexes = index_ids(self.teachers.iter().map(|x| x.id), "teacher")?;
+        let room_indexes = index_ids(self.rooms.iter().map(|x| x.id), "room")?;
+        let student_group_indexes =
+            index_ids(self.student_groups.iter().map(|x| x.id), "student group")?;
+        index_ids(self.classes.iter().map(|x| x.id), "class")?;
+        let mut classes = Vec::new();
+        for (index, entry) in self.classes.iter().enumerate() {
+            if entry.rooms.is_empty() {
+                return Err(format!("class {} ({}): no rooms", entry.id, entry.name));
+            }
+            if entry.serial_size == 0 {
+                return Err(format!("class {} ({}): serial size is 0", entry.id, entry.name));
+            }
+            classes.push(class::Class {
+                id: entry.id,
+                index,
+                num_of_students: entry.num_of_students,
+                name: entry.name.clone(),
+                teacher_indexes: resolve_ids(&entry.teachers, &teacher_indexes, "teacher", entry)?,
+                room_candidates_indexes: resolve_ids(&entry.rooms, &room_indexes, "room", entry)?,
+                students_group_indexes: resolve_ids(
+                    &entry.student_groups,
+                    &student_group_indexes,
+                    "student group",
+                    entry,
+                )?,
+                serial_size: entry.serial_size,
+                unavailable_periods: entry.unavailable_periods.clone(),
+            });
+        }
+        Ok(Input {
+            classes,
+            rooms: self
+                .rooms
+                .into_iter()
+                .enumerate()
+                .map(|(index, x)| room::Room {
+                    id: x.id,
+                    index,
+                    name: x.name,
+                    capacity: x.capacity,
+                })
+                .collect(),
+            student_groups: self
+                .student_groups
+                .into_iter()
+                .enumerate()
+                .map(|(index, x)| student_group::StudentGroup {
+                    id: x.id,
+                    name: x.name,
+                    index,
+                })
+                .collect(),
+            teachers: self
+                .teachers
+                .into_iter()
+                .enumerate()
+                .map(|(index, x)| teacher::Teacher {
+                    id: x.id,
+                    index,
+                    name: x.name,
+                    absent_days: x.absent_periods,
+                })
+                .collect(),
+        })
+    }
+}
+
+enum InputFormat {
+    Json,
+    Toml,
+    //teachers.csv等が入ったディレクトリ
+    CsvDir,
+}
+
+fn get_format(path: &Path) -> Result<InputFormat, String> {
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("json") => Ok(InputFormat::Json),
+        Some("toml") => Ok(InputFormat::Toml),
+        None => Ok(InputFormat::CsvDir),
+        Some(x) => Err(format!("unknown input format: {}", x)),
+    }
+}
+
+pub fn load_input(path: &Path) -> Result<Input, Box<dyn Error>> {
+    match get_format(path)? {
+        InputFormat::Json => {
+            let file: InputFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            Ok(file.into_input()?)
+        }
+        InputFormat::Toml => {
+            let file: InputFile = toml::from_str(&std::fs::read_to_string(path)?)?;
+            Ok(file.into_input()?)
+        }
+        InputFormat::CsvDir => Input::read_from_csv_dir(path),
+    }
+}
+
+pub fn save_input(input: &Input, path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match get_format(path)? {
+        InputFormat::Json => {
+            let text = serde_json::to_string_pretty(&InputFile::from_input(input))?;
+            std::fs::write(path, text)?;
+        }
+        InputFormat::Toml => {
+            let text = toml::to_string_pretty(&InputFile::from_input(input))?;
+            std::fs::write(path, text)?;
+        }
+        InputFormat::CsvDir => input.write_to_csv_dir(path)?,
+    }
+    Ok(())
+}
+
+// 拡張子で形式を判断する: .json, .toml, 拡張子なしはCSVのディレクトリ
+pub fn convert_input(from: &Path, to: &Path) -> Result<(), Box<dyn Error>> {
+    let input = load_input(from)?;
+    save_input(&input, to)
+}
+
+#[tauri::command]
+pub fn handle_load_input_file(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<(), CommandError> {
+    info!("called handle_load_input_file {}", path);
+    let input = load_input(Path::new(&path))?;
+    session_manager.session.lock_state()?.load_input(input);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn handle_save_input_file(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<(), CommandError> {
+    info!("called handle_save_input_file {}", path);
+    let session = session_manager.session.lock_state()?;
+    save_input(session.get_input()?, Path::new(&path)).map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub fn handle_convert_input(from: String, to: String) -> Result<(), CommandError> {
+    info!("called handle_convert_input {} {}", from, to);
+    convert_input(Path::new(&from), Path::new(&to)).map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //教員や学生グループが空の授業もCSVを通して元に戻る
+    #[test]
+    fn csv_round_trip_keeps_empty_lists() {
+        let json = r#"{
+            "version": 1,
+            "teachers": [{"id": 1, "name": "T1", "absentPeriods": [2, 3]}],
+            "rooms": [{"id": 10, "name": "R1", "capacity": 40}],
+            "studentGroups": [{"id": 20, "name": "G1"}],
+            "classes": [
+                {"id": 100, "name": "C1", "teachers": [1], "rooms": [10],
+                 "studentGroups": [20], "numOfStudents": 30, "serialSize": 2,
+                 "unavailablePeriods": [0]},
+                {"id": 101, "name": "C2", "rooms": [10], "numOfStudents": 0}
+            ]
+        }"#;
+        let file: InputFile = serde_json::from_str(json).unwrap();
+        let input = file.clone().into_input().unwrap();
+        let dir = std::env::temp_dir().join(format!("fun-timetabling-csv-{}", std::process::id()));
+        input.write_to_csv_dir(&dir).unwrap();
+        let read = Input::read_from_csv_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let read = InputFile::from_input(&read.unwrap());
+        assert_eq!(
+            serde_json::to_value(&read).unwrap(),
+            serde_json::to_value(&file).unwrap()
+        );
+    }
+}
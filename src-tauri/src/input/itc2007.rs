@@ -0,0 +1,268 @@
+// ITC-2007 track 3 (curriculum-based course timetabling) の.ctt形式
+// courses -> classes (1 lecture = 1 class), curricula -> student groups
+// MinWorkingDaysとcurriculum compactnessは対応する制約がないので読み捨てる
+use super::{class, room, student_group, teacher, Input};
+use crate::algorithm::time_table::TimeTable;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use crate::error::{CommandError, LockState};
+use crate::session::SessionManager;
+
+pub struct CttInstance {
+    pub name: String,
+    pub num_of_days: usize,
+    pub periods_per_day: usize,
+    pub input: Input,
+}
+
+impl CttInstance {
+    pub fn get_num_of_periods(&self) -> usize {
+        self.num_of_days * self.periods_per_day
+    }
+}
+
+struct CttCourse {
+    name: String,
+    teacher: String,
+    num_of_lectures: usize,
+    num_of_students: usize,
+}
+
+fn parse_field<T: std::str::FromStr>(
+    value: Option<&str>,
+    line_number: usize,
+    field: &str,
+) -> Result<T, String> {
+    let value = value.ok_or(format!("line {}: missing {}", line_number, field))?;
+    value
+        .parse::<T>()
+        .map_err(|_| format!("line {}: invalid {}: {}", line_number, field, value))
+}
+
+pub fn parse_ctt(text: &str) -> Result<CttInstance, Box<dyn Error>> {
+    let mut header = HashMap::<String, String>::new();
+    let mut courses = Vec::<CttCourse>::new();
+    let mut rooms = Vec::<room::Room>::new();
+    let mut curricula = Vec::<(String, Vec<String>)>::new();
+    let mut unavailabilities = Vec::<(String, usize, usize)>::new();
+    let mut section = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "END." {
+            break;
+        }
+        if line.ends_with(':') && !line.contains(' ') {
+            section = line.trim_end_matches(':').to_string();
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        match section.as_str() {
+            "" => {
+                let (key, value) = line
+                    .split_once(':')
+                    .ok_or(format!("line {}: invalid header: {}", line_number, line))?;
+                header.insert(key.trim().to_string(), value.trim().to_string());
+            }
+            "COURSES" => {
+                let name = parse_field::<String>(fields.next(), line_number, "course")?;
+                let teacher = parse_field::<String>(fields.next(), line_number, "teacher")?;
+                let num_of_lectures = parse_field(fields.next(), line_number, "lectures")?;
+                let _min_working_days =
+                    parse_field::<usize>(fields.next(), line_number, "min working days")?;
+                let num_of_students = parse_field(fields.next(), line_number, "students")?;
+                courses.push(CttCourse {
+                    name,
+                    teacher,
+                    num_of_lectures,
+                    num_of_students,
+                });
+            }
+            "ROOMS" => {
+                let name = parse_field::<String>(fields.next(), line_number, "room")?;
+                let capacity = parse_field(fields.next(), line_number, "capacity")?;
+                rooms.push(room::Room {
+                    id: rooms.len(),
+                    index: rooms.len(),
+                    name,
+                    capacity,
+                });
+            }
+            "CURRICULA" => {
+                let name = parse_field::<String>(fields.next(), line_number, "curriculum")?;
+                let num_of_courses = parse_field::<usize>(fields.next(), line_number, "courses")?;
+                let members = fields.map(|x| x.to_string()).collect::<Vec<String>>();
+                if members.len() != num_of_courses {
+                    return Err(format!(
+                        "line {}: curriculum {} has {} courses but {} are listed",
+                        line_number,
+                        name,
+                        num_of_courses,
+                        members.len()
+                    )
+                    .into());
+                }
+                curricula.push((name, members));
+            }
+            "UNAVAILABILITY_CONSTRAINTS" => {
+                let course = parse_field::<String>(fields.next(), line_number, "course")?;
+                let day = parse_field(fields.next(), line_number, "day")?;
+                let period = parse_field(fields.next(), line_number, "period")?;
+                unavailabilities.push((course, day, period));
+            }
+            _ => {
+                warn!("line {}: ignored section {}", line_number, section);
+            }
+        }
+    }
+
+    let get_header = |key: &str| -> Result<usize, String> {
+        let value = header.get(key).ok_or(format!("missing header: {}", key))?;
+        value
+            .parse::<usize>()
+            .map_err(|_| format!("invalid header {}: {}", key, value))
+    };
+    let num_of_days = get_header("Days")?;
+    let periods_per_day = get_header("Periods_per_day")?;
+    if courses.len() != get_header("Courses")? || rooms.len() != get_header("Rooms")? {
+        return Err("number of courses or rooms does not match the header".into());
+    }
+
+    let mut teachers = Vec::<teacher::Teacher>::new();
+    for course in courses.iter() {
+        if !teachers.iter().any(|x| x.name == course.teacher) {
+            teachers.push(teacher::Teacher {
+                id: teachers.len(),
+                index: teachers.len(),
+                name: course.teacher.clone(),
+                absent_days: Vec::new(),
+            });
+        }
+    }
+    let student_groups = curricula
+        .iter()
+        .enumerate()
+        .map(|(index, (name, _))| student_group::StudentGroup {
+            id: index,
+            name: name.clone(),
+            index,
+        })
+        .collect::<Vec<student_group::StudentGroup>>();
+    let mut unavailable_periods = HashMap::<&str, Vec<usize>>::new();
+    for (course, day, period) in unavailabilities.iter() {
+        if !courses.iter().any(|x| &x.name == course) {
+            return Err(format!("unavailability for unknown course: {}", course).into());
+        }
+        if *day >= num_of_days || *period >= periods_per_day {
+            return Err(format!("unavailability out of range: {} {} {}", course, day, period).into());
+        }
+        unavailable_periods
+            .entry(course.as_str())
+            .or_default()
+            .push(day * periods_per_day + period);
+    }
+
+    let mut classes = Vec::<class::Class>::new();
+    for course in courses.iter() {
+        let teacher_index = teachers
+            .iter()
+            .position(|x| x.name == course.teacher)
+            .unwrap();
+        let students_group_indexes = curricula
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, members))| members.contains(&course.name))
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+        //同じcourseのlectureは同じ教員なので別のコマに入る
+        for _ in 0..course.num_of_lectures {
+            classes.push(class::Class {
+                id: classes.len(),
+                index: classes.len(),
+                num_of_students: course.num_of_students,
+                name: course.name.clone(),
+                teacher_indexes: vec![teacher_index],
+                room_candidates_indexes: (0..rooms.len()).collect(),
+                students_group_indexes: students_group_indexes.clone(),
+                serial_size: 1,
+                unavailable_periods: unavailable_periods
+                    .get(course.name.as_str())
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+    }
+    for (name, members) in curricula.iter() {
+        for member in members.iter() {
+            if !courses.iter().any(|x| &x.name == member) {
+                return Err(format!("curriculum {} has unknown course: {}", name, member).into());
+            }
+        }
+    }
+
+    Ok(CttInstance {
+        name: header.get("Name").cloned().unwrap_or_default(),
+        num_of_days,
+        periods_per_day,
+        input: Input {
+            classes,
+            rooms,
+            student_groups,
+            teachers,
+        },
+    })
+}
+
+pub fn read_ctt(path: &str) -> Result<CttInstance, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)?;
+    parse_ctt(&text)
+}
+
+// 1行に1 lecture: <CourseID> <RoomID> <Day> <Day_Period>
+pub fn create_ctt_solution(timetable: &TimeTable, input: &Input, periods_per_day: usize) -> String {
+    let mut res = String::new();
+    for cell in timetable.class_list.iter().flatten() {
+        res.push_str(&format!(
+            "{} {} {} {}\n",
+            input.get_classes()[cell.class_index].name,
+            input.get_rooms()[cell.room].name,
+            cell.period / periods_per_day,
+            cell.period % periods_per_day
+        ));
+    }
+    res
+}
+
+pub fn write_ctt_solution(
+    timetable: &TimeTable,
+    input: &Input,
+    periods_per_day: usize,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut file = File::create(path)?;
+    file.write_all(create_ctt_solution(timetable, input, periods_per_day).as_bytes())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn handle_export_ctt_solution(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<(), CommandError> {
+    info!("called handle_export_ctt_solution {}", path);
+    let session = session_manager.session.lock_state()?;
+    let (timetable, solver) = session.get_timetable_and_solver()?;
+    write_ctt_solution(
+        timetable,
+        &solver.input,
+        solver.parameters.num_of_day_lengths,
+        &path,
+    )
+    .map_err(CommandError::from)
+}
@@ -1,19 +1,33 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 use std::cmp::min;
-use std::sync::Mutex;
 use tauri::Manager;
 mod algorithm;
 mod input;
+mod project;
+mod export;
 use std::error::Error;
 mod table_editor;
-use algorithm::aco::aco_parameters::AcoParametersManager;
-use algorithm::aco::aco_solver::ACOSolverManager;
+mod error;
+mod session;
 use algorithm::time_table;
-use input::InputManager;
+use algorithm::time_table::resolve::{self, MovedClass};
+use input::fet::{handle_export_fet, read_fet};
+use input::input_file::{
+    convert_input, handle_convert_input, handle_load_input_file, handle_save_input_file,
+};
+use input::itc2007::{handle_export_ctt_solution, read_ctt, write_ctt_solution};
+use algorithm::time_table::snapshot::{
+    handle_delete_snapshot, handle_diff_snapshots, handle_list_snapshots, handle_restore_snapshot,
+    handle_save_snapshot,
+};
 use std::time::Instant;
 use log::info;
 use std::env;
+use algorithm::aco::stopping_criteria::{RunSummary, StoppingCriteria};
+use serde::{Deserialize, Serialize};
+use error::{CommandError, LockState};
+use session::{handle_get_session_state, Session, SessionManager};
 
 fn reset_aco_solver(
     input: &input::Input,
@@ -33,185 +47,390 @@ fn reset_aco_solver(
         best_ant: None,
         super_ant: None,
         cnt_super_not_change: 0,
+        run_events: Vec::new(),
+        cnt_since_restart: 0,
         input: input.clone(),
         cnt: 0,
     });
     return solver.unwrap();
 }
 
+fn default_aco_parameters(input: &input::Input) -> algorithm::aco::aco_parameters::AcoParameters {
+    return algorithm::aco::aco_parameters::AcoParameters {
+        num_of_ants: 3,
+        num_of_classes: input.get_classes().len(),
+        num_of_rooms: input.get_rooms().len(),
+        num_of_periods: 5*5,
+        num_of_day_lengths: 5,
+        num_of_teachers: input.get_teachers().len(),
+        num_of_students: input.get_student_groups().len(),
+        size_of_frame: 1,
+        
+        alpha: 1.0,// T 1.0 : K 2.0
+        beta: 2.0, // T 2.0 : K 8.0
+        q: 10.0,// T 10.0 : K 1.0
+        rou:  0.5, // T 0.5 : K 0.95
+        tau_min: 0.001,// T 0.001 : K 0.01
+        tau_max: 100000.0, // T 100000.0 : K 10.0
+        max_iterations: 100,
+        ant_prob_random: 0.001,
+        super_not_change: 10000,
+        class_ordering: algorithm::aco::class_ordering::ClassOrdering::Random,
+        min_branching_factor: 0.0,
+        restart_ratio: 1.0,
+    };
+}
+
 #[tauri::command]
 fn handle_adapt_input(
-    input_manager: tauri::State<'_, InputManager>,
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-    aco_parameters_manager: tauri::State<'_, AcoParametersManager>,
-) -> Result<(), String> {
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<(), CommandError> {
     info!("called handle_adapt_input");
-    let input = input_manager.input.lock().unwrap();
-    if let Some(input) = input.clone() {
-        let parameters = algorithm::aco::aco_parameters::AcoParameters {
-            num_of_ants: 3,
-            num_of_classes: input.get_classes().len(),
-            num_of_rooms: input.get_rooms().len(),
-            num_of_periods: 5*5,
-            num_of_day_lengths: 5,
-            num_of_teachers: input.get_teachers().len(),
-            num_of_students: input.get_student_groups().len(),
-            size_of_frame: 1,
-            
-            alpha: 1.0,// T 1.0 : K 2.0
-            beta: 2.0, // T 2.0 : K 8.0
-            q: 10.0,// T 10.0 : K 1.0
-            rou:  0.5, // T 0.5 : K 0.95
-            tau_min: 0.001,// T 0.001 : K 0.01
-            tau_max: 100000.0, // T 100000.0 : K 10.0
-            max_iterations: 100,
-            ant_prob_random: 0.001,
-            super_not_change: 10000,
-        };
-        let solver = reset_aco_solver(&input, &parameters);
-        let mut manarged_solver = solver_manager.solver.lock().unwrap();
-        manarged_solver.replace(solver);
-        let mut managed_parameters = aco_parameters_manager.parameters.lock().unwrap();
-        managed_parameters.replace(parameters);
-    } else {
-        println!("no input!");
-    }
+    let mut session = session_manager.session.lock_state()?;
+    let input = session.get_input()?;
+    let parameters = default_aco_parameters(input);
+    let solver = reset_aco_solver(input, &parameters);
+    session.prepare_solver(parameters, solver)?;
     Ok(())
 }
 use input::handle_set_input;
 
+//外部形式から読み込んだ場合は曜日数・時限数を合わせる
+fn sized_aco_parameters(
+    input: &input::Input,
+    num_of_periods: usize,
+    num_of_day_lengths: usize,
+) -> algorithm::aco::aco_parameters::AcoParameters {
+    let mut parameters = default_aco_parameters(input);
+    parameters.num_of_periods = num_of_periods;
+    parameters.num_of_day_lengths = num_of_day_lengths;
+    return parameters;
+}
+
+fn replace_input(
+    session: &mut Session,
+    input: input::Input,
+    parameters: algorithm::aco::aco_parameters::AcoParameters,
+) -> Result<(), CommandError> {
+    let solver = reset_aco_solver(&input, &parameters);
+    session.load_input(input);
+    return session.prepare_solver(parameters, solver);
+}
+
+//.cttを読み込んでinputとsolverを置き換える、元の時間割は破棄する
+#[tauri::command]
+fn handle_import_ctt(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<String, CommandError> {
+    info!("called handle_import_ctt {}", path);
+    let instance = read_ctt(&path)?;
+    let parameters = sized_aco_parameters(
+        &instance.input,
+        instance.get_num_of_periods(),
+        instance.periods_per_day,
+    );
+    let mut session = session_manager.session.lock_state()?;
+    replace_input(
+        &mut session,
+        instance.input,
+        parameters,
+    )?;
+    return Ok(instance.name);
+}
+
+//.fetを読み込んでinputとsolverを置き換える、読み捨てた制約の警告を返す
+#[tauri::command]
+fn handle_import_fet(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<Vec<String>, CommandError> {
+    info!("called handle_import_fet {}", path);
+    let instance = read_fet(&path)?;
+    info!("imported {} with {} warnings", instance.name, instance.warnings.len());
+    let parameters = sized_aco_parameters(
+        &instance.input,
+        instance.get_num_of_periods(),
+        instance.hours_per_day,
+    );
+    let mut session = session_manager.session.lock_state()?;
+    replace_input(
+        &mut session,
+        instance.input,
+        parameters,
+    )?;
+    return Ok(instance.warnings);
+}
+
 #[tauri::command]
 fn handle_aco_run_once(
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-    timetable_manager: tauri::State<'_, time_table::TimeTableManager>,
-) -> Result<time_table::TimeTable, String> {
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<time_table::TimeTable, CommandError> {
     info!("called handle_aco_run_once");
-    let mut managed_solver = solver_manager.solver.lock().unwrap();
-
-    if let Some(solver) = managed_solver.as_mut() {
-        let mut run_cnt = 0;
-        let start = Instant::now();
-        for _ in 0..10 {
-            solver.run_aco_times(1);
-            run_cnt += 1;
-            if let Some(best_ant) = &solver.best_ant {
-                println!("length:{}", best_ant.calc_all_path_length(solver.colony.get_graph()));
-                if best_ant.calc_all_path_length(solver.colony.get_graph()) <= 0.001 {
-                    break;
-                }
-            }
-        }
-        let duaration = start.elapsed();
-        println!("times:{:?},{:?}", run_cnt, duaration);
-        let res = time_table::convert_solver_to_timetable(solver).map_err(|e| e.to_string())?;
-        time_table::save_timetable(timetable_manager, res.clone());
-        return Ok(res);
-    }
-    return Err("No ACOSolver".to_string());
+    let criteria = StoppingCriteria {
+        max_generations: Some(10),
+        target_score: Some(0.001),
+        ..Default::default()
+    };
+    let result = run_aco_with_criteria(session_manager, &criteria)?;
+    return Ok(result.timetable);
 }
 
 #[tauri::command]
 fn handle_aco_run_no_violations(
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-    timetable_manager: tauri::State<'_, time_table::TimeTableManager>,
-) -> Result<time_table::TimeTable, String> {
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<time_table::TimeTable, CommandError> {
     info!("called handle_aco_run_no_violations");
-    let mut managed_solver = solver_manager.solver.lock().unwrap();
-    if let Some(solver) = managed_solver.as_mut() {
-        let start = Instant::now();
-        for gen in 0..2000 {
-            solver.run_aco_times(1);
-            if let Some(best_ant) = &solver.best_ant {
-                if best_ant.calc_all_path_length(solver.colony.get_graph()) <= 0.001 {
-                    break;
-                }
-                println!("{}", best_ant.count_violations(solver.colony.get_graph()));
-            }
+    let criteria = StoppingCriteria {
+        max_generations: Some(2000),
+        target_score: Some(0.001),
+        ..Default::default()
+    };
+    let result = run_aco_with_criteria(session_manager, &criteria)?;
+    return Ok(result.timetable);
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AcoRunResult {
+    timetable: time_table::TimeTable,
+    summary: RunSummary,
+}
+
+#[tauri::command]
+fn handle_aco_run_with_criteria(
+    session_manager: tauri::State<'_, SessionManager>,
+    criteria: StoppingCriteria,
+) -> Result<AcoRunResult, CommandError> {
+    info!("called handle_aco_run_with_criteria {:?}", criteria);
+    return run_aco_with_criteria(session_manager, &criteria);
+}
+
+fn run_aco_with_criteria(
+    session_manager: tauri::State<'_, SessionManager>,
+    criteria: &StoppingCriteria,
+) -> Result<AcoRunResult, CommandError> {
+    let mut session = session_manager.session.lock_state()?;
+    let solver = session.get_solver_mut()?;
+    let summary = solver.run_aco_until(criteria)?;
+    info!(
+        "{:?},{:?},{:?}s",
+        summary.reason, summary.generations, summary.elapsed_secs
+    );
+    let timetable = time_table::convert_solver_to_timetable(solver)?;
+    session.set_timetable(timetable)?;
+    session.annotate()?;
+    return Ok(AcoRunResult {
+        timetable: session.clone_timetable()?,
+        summary,
+    });
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ResolveResult {
+    timetable: time_table::TimeTable,
+    summary: RunSummary,
+    moved_classes: Vec<MovedClass>,
+}
+
+const RESOLVE_SEED_STRENGTH: f64 = 0.5;
+
+//入力を変更した後、前の時間割からなるべく動かさないように再計画する
+#[tauri::command]
+fn handle_resolve_with_input(
+    session_manager: tauri::State<'_, SessionManager>,
+    criteria: StoppingCriteria,
+    revision: Option<u64>,
+) -> Result<ResolveResult, CommandError> {
+    info!("called handle_resolve_with_input {:?}", criteria);
+    let mut session = session_manager.session.lock_state()?;
+    session.check_revision(revision)?;
+    let input = session.get_input()?.clone();
+    let previous = session
+        .get_previous_solution()
+        .ok_or(CommandError::NoTimetable)?
+        .clone();
+    let previous_timetable = previous.timetable;
+    let previous_input = previous.input;
+
+    let mut parameters = previous.parameters;
+    parameters.num_of_classes = input.get_classes().len();
+    parameters.num_of_rooms = input.get_rooms().len();
+    parameters.num_of_teachers = input.get_teachers().len();
+    parameters.num_of_students = input.get_student_groups().len();
+    let previous_assignments =
+        resolve::calc_previous_assignments(&previous_timetable, &previous_input, &input);
+    let previous_locks = resolve::calc_previous_locks(
+        &previous_timetable,
+        &previous_input,
+        &input,
+        parameters.num_of_periods,
+    );
+    let mut solver = reset_aco_solver(&input, &parameters);
+    solver.colony.get_graph_as_mut().load_cells(&previous_locks);
+    solver.set_previous_assignments(previous_assignments, RESOLVE_SEED_STRENGTH);
+    let summary = solver.run_aco_until(&criteria)?;
+    solver.clear_previous_assignments();
+    let timetable =
+        time_table::convert_solver_to_timetable(&solver)?;
+    let moved_classes =
+        resolve::calc_moved_classes(&previous_timetable, &previous_input, &timetable, &input);
+    info!("moved {} classes", moved_classes.len());
+
+    session.prepare_solver(parameters, solver)?;
+    session.set_timetable(timetable)?;
+    session.annotate()?;
+    return Ok(ResolveResult {
+        timetable: session.clone_timetable()?,
+        summary,
+        moved_classes,
+    });
+}
+
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    if let Some(position) = args.iter().position(|x| x == name) {
+        if position + 1 >= args.len() {
+            return Err(format!("missing value for {}", name));
         }
-        let duaration = start.elapsed();
-        println!("{:?},{:?}", solver.cnt, duaration);
-        let res = time_table::convert_solver_to_timetable(solver).map_err(|e| e.to_string())?;
-        time_table::save_timetable(timetable_manager, res.clone());
-        return Ok(res);
+        let value = args.remove(position + 1);
+        args.remove(position);
+        return Ok(Some(value));
     }
-    return Err("No ACOSolver".to_string());
+    return Ok(None);
+}
+
+// fun-timetabling solve [--time-limit <secs>] [--max-generations <n>] [--target-score <score>]
+//                       [--zero-violations] [--no-improvement <n>]
+//                       [--ctt <instance.ctt>] [--solution <out.sol>]
+fn run_headless(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut args = args.to_vec();
+    let ctt_path = take_option(&mut args, "--ctt")?;
+    let solution_path = take_option(&mut args, "--solution")?;
+    let mut criteria = StoppingCriteria::parse_args(&args)?;
+    if !criteria.is_bounded() {
+        criteria.max_generations = Some(2000);
+    }
+    let (input, parameters) = match &ctt_path {
+        Some(path) => {
+            let instance = read_ctt(path)?;
+            let parameters = sized_aco_parameters(
+                &instance.input,
+                instance.get_num_of_periods(),
+                instance.periods_per_day,
+            );
+            (instance.input, parameters)
+        }
+        None => {
+            let input = input::Input::read_from_csv_dir(std::path::Path::new(input::CSV_DIR))?;
+            let parameters = default_aco_parameters(&input);
+            (input, parameters)
+        }
+    };
+    let mut solver = reset_aco_solver(&input, &parameters);
+    let summary = solver.run_aco_until(&criteria)?;
+    println!("{}", serde_json::to_string(&summary)?);
+    if let Some(path) = solution_path {
+        let timetable = time_table::convert_solver_to_timetable(&solver)?;
+        write_ctt_solution(&timetable, &solver.input, parameters.num_of_day_lengths, &path)?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
 fn handle_calc_performance(
-    input_manager: tauri::State<'_, InputManager>,
-    aco_parameters_manager: tauri::State<'_, AcoParametersManager>,
-    solver_manager: tauri::State<'_, ACOSolverManager>,
-    timetable_manager: tauri::State<'_, time_table::TimeTableManager>,
-) -> Result<(), String> {
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<(), CommandError> {
     info!("called handle_calc_paformance");
+    let (input, parameters) = {
+        let session = session_manager.session.lock_state()?;
+        (session.get_input()?.clone(), session.get_parameters()?.clone())
+    };
     let mut times = Vec::<f64>::new();
     let mut generations = Vec::<usize>::new();
-    let mut is_solved = Vec::<bool>::new();
     let mut minimums = Vec::<usize>::new();
     for i in 0..50 {
-        let mut is_cur_solve = false;
-        if let Some(input) = input_manager.input.lock().unwrap().as_ref() {
-            if let Some(parameters) = aco_parameters_manager.parameters.lock().unwrap().as_ref() {
-                let mut solver = reset_aco_solver(input, parameters);
-                let start = Instant::now();
-                let mut minimum = 100000000;
-                let mut count_cur_violation = 100000000;
-                for _ in 0..2000 {
-                        solver.run_aco_times(1);
-                        if let Some(best_ant) = &solver.best_ant {
-                            count_cur_violation = best_ant.count_violations(solver.colony.get_graph());
-                            
-                            if minimum > count_cur_violation {
-                                minimum = count_cur_violation;
-                            }
-                            if count_cur_violation <= 0 {
-                                break;
-                            }
-                    }
+        let mut solver = reset_aco_solver(&input, &parameters);
+        let start = Instant::now();
+        let mut minimum = 100000000;
+        let mut count_cur_violation = 100000000;
+        for _ in 0..2000 {
+            solver.run_aco_times(1);
+            if let Some(best_ant) = &solver.best_ant {
+                count_cur_violation = best_ant.count_violations(solver.colony.get_graph());
+                if minimum > count_cur_violation {
+                    minimum = count_cur_violation;
                 }
-                let duaration = start.elapsed();
-                times.push(duaration.as_secs_f64());
-                generations.push(solver.cnt);
-                minimums.push(minimum);
-                if let Some(best_ant) = &solver.best_ant {
-                    if count_cur_violation <= 0 {
-                        is_solved.push(true);
-                        is_cur_solve = true;
-                    } else {
-                        is_solved.push(false);
-                    }
+                if count_cur_violation <= 0 {
+                    break;
                 }
             }
         }
-        println!("finished:{},{:},{}", i,is_cur_solve,minimums[i]);
+        let duaration = start.elapsed();
+        times.push(duaration.as_secs_f64());
+        generations.push(solver.cnt);
+        minimums.push(minimum);
+        info!("finished:{},{:},{}", i, count_cur_violation <= 0, minimum);
     }
     let generation_average = generations.iter().sum::<usize>() as f64 / generations.len() as f64;
     let average = times.iter().sum::<f64>() / times.len() as f64;
-    for(i, time) in times.iter().enumerate() {
-        println!("{},{},{},{}", i, time,generations[i],minimums[i]);
+    for (i, time) in times.iter().enumerate() {
+        info!("{},{},{},{}", i, time, generations[i], minimums[i]);
     }
-    println!("time_average:{}", average);
-    println!("gen_average:{}", generation_average);
-    
-    return Err("No ACOSolver".to_string());
+    info!("time_average:{}", average);
+    info!("gen_average:{}", generation_average);
+    Ok(())
 }
 use algorithm::aco::aco_parameters::handle_get_periods;
 use algorithm::aco::aco_solver::handle_one_hot_pheromone;
 use algorithm::aco::aco_solver::handle_read_cells;
+use algorithm::aco::aco_solver::handle_set_class_ordering;
+use algorithm::aco::aco_solver::handle_get_run_events;
+use algorithm::aco::aco_solver::handle_warm_start;
 use input::handle_get_rooms;
 use table_editor::handle_get_table;
-use time_table::dump_timetable;
+use export::ical::handle_export_ical;
+use export::report::{handle_export_html_report, handle_export_pdf_report};
+use export::sheet::{handle_export_csv, handle_export_xlsx};
+use project::dump_timetable;
+use project::handle_open_project;
+use project::handle_save_project;
 use time_table::handle_swap_cell;
+use time_table::handle_set_lock;
+use time_table::handle_recompute_violations;
 use time_table::handle_switch_lock;
 use time_table::is_swappable;
-use time_table::load_timetable;
+use time_table::moves::handle_get_move_plan;
+use time_table::explanation::{handle_get_language, handle_set_language};
+use time_table::suggestion::handle_get_move_suggestions;
+use time_table::weak_placement::{
+    handle_get_weak_placement_config, handle_get_weak_placements, handle_set_weak_placement_config,
+};
+use project::load_timetable;
 use time_table::handle_lock_no_violation;
 use time_table::handle_unlock_violation;
+use time_table::bulk_lock::{handle_lock_all_except, handle_lock_selection, handle_unlock_selection};
+use time_table::handle_undo;
+use time_table::handle_redo;
+use time_table::handle_get_history;
+use algorithm::time_table::projection::{
+    handle_get_all_projected_timetables, handle_get_projected_timetable, handle_get_view_names,
+};
 
 fn main() -> Result<(), Box<dyn Error>> {
     env::set_var("RUST_LOG", "info");
     env_logger::init();
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "solve" {
+        return run_headless(&args[2..]);
+    }
+    // fun-timetabling convert <from> <to>  (.json, .toml, or a csv directory)
+    if args.len() > 1 && args[1] == "convert" {
+        if args.len() != 4 {
+            return Err("usage: convert <from> <to>".into());
+        }
+        return convert_input(std::path::Path::new(&args[2]), std::path::Path::new(&args[3]));
+    }
     //let input = input::Input::new();
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
@@ -231,25 +450,53 @@ fn main() -> Result<(), Box<dyn Error>> {
             load_timetable,
             handle_calc_performance,
             handle_lock_no_violation,
-            handle_unlock_violation
+            handle_unlock_violation,
+            handle_set_class_ordering,
+            handle_get_run_events,
+            handle_aco_run_with_criteria,
+            handle_warm_start,
+            handle_resolve_with_input,
+            handle_save_project,
+            handle_open_project,
+            handle_save_snapshot,
+            handle_list_snapshots,
+            handle_restore_snapshot,
+            handle_delete_snapshot,
+            handle_diff_snapshots,
+            handle_undo,
+            handle_redo,
+            handle_get_history,
+            handle_get_view_names,
+            handle_get_projected_timetable,
+            handle_get_all_projected_timetables,
+            handle_export_ical,
+            handle_export_csv,
+            handle_export_xlsx,
+            handle_export_html_report,
+            handle_export_pdf_report,
+            handle_import_ctt,
+            handle_export_ctt_solution,
+            handle_import_fet,
+            handle_export_fet,
+            handle_load_input_file,
+            handle_save_input_file,
+            handle_convert_input,
+            handle_get_move_plan,
+            handle_recompute_violations,
+            handle_get_move_suggestions,
+            handle_get_weak_placements,
+            handle_get_weak_placement_config,
+            handle_set_weak_placement_config,
+            handle_get_language,
+            handle_set_language,
+            handle_set_lock,
+            handle_lock_selection,
+            handle_unlock_selection,
+            handle_lock_all_except,
+            handle_get_session_state
         ])
         .setup(|app| {
-            let input_manager = InputManager {
-                input: Mutex::new(None),
-            };
-            app.manage(input_manager);
-            let solver_manager = ACOSolverManager {
-                solver: Mutex::new(None),
-            };
-            app.manage(solver_manager);
-            let timetable_manager = time_table::TimeTableManager {
-                timetable_manager: Mutex::new(None),
-            };
-            app.manage(timetable_manager);
-            let aco_parameters_manager = AcoParametersManager {
-                parameters: Mutex::new(None),
-            };
-            app.manage(aco_parameters_manager);
+            app.manage(SessionManager::new());
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -0,0 +1,408 @@
+use crate::algorithm::aco::aco_parameters::AcoParameters;
+use crate::algorithm::aco::aco_solver::ACOSolver;
+use crate::algorithm::aco::graph::ClassLock;
+use crate::algorithm::time_table::snapshot::Snapshot;
+use crate::algorithm::time_table::TimeTable;
+use crate::input::Input;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::api::path::config_dir;
+use crate::error::{CommandError, LockState};
+use crate::session::{Session, SessionManager};
+
+//保存形式を変えたらここを上げて、migrateに変換を追加する
+pub const PROJECT_VERSION: u64 = 3;
+
+const DUMP_PATH: &str = "ClassistAnt";
+const DUMP_PROJECT_FILE: &str = "project.json";
+const LEGACY_DUMP_TIMETABLE_FILE: &str = "timetable.json";
+const LEGACY_DUMP_SOLVER_FILE: &str = "solver.json";
+
+//入力、パラメータ、solver、時間割、ロックをまとめた保存ファイル
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub version: u64,
+    pub input: Input,
+    pub parameters: AcoParameters,
+    pub solver: Option<ACOSolver>,
+    pub timetable: Option<TimeTable>,
+    pub locks: Vec<Option<ClassLock>>,
+    #[serde(default)]
+    pub snapshots: Vec<Snapshot>,
+}
+
+impl Project {
+    pub fn new(
+        input: Input,
+        parameters: AcoParameters,
+        solver: Option<ACOSolver>,
+        timetable: Option<TimeTable>,
+    ) -> Project {
+        let mut locks = vec![None; input.get_classes().len()];
+        if let Some(timetable) = &timetable {
+            for cell in timetable.class_list.iter().flatten() {
+                if cell.class_index < locks.len() {
+                    locks[cell.class_index] = cell.get_lock();
+                }
+            }
+        } else if let Some(solver) = &solver {
+            locks = solver.colony.get_graph().get_all_classes_is_locked().clone();
+        }
+        Project {
+            version: PROJECT_VERSION,
+            input,
+            parameters,
+            solver,
+            timetable,
+            locks,
+            snapshots: Vec::new(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self)?;
+        let mut file = File::create(path)?;
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn open(path: &Path) -> Result<Project, Box<dyn Error>> {
+        let value = read_json(path)?;
+        let mut project = Project::migrate(value)?;
+        project.apply_locks();
+        Ok(project)
+    }
+
+    fn migrate(value: Value) -> Result<Project, Box<dyn Error>> {
+        let version = match value.get("version") {
+            Some(version) => version.as_u64().ok_or("invalid project version")?,
+            //version 0: 以前のsolver.json
+            None if value.get("colony").is_some() => 0,
+            None => return Err("not a ClassistAnt project file".into()),
+        };
+        if version > PROJECT_VERSION {
+            return Err(format!(
+                "project file version {} is newer than supported version {}",
+                version, PROJECT_VERSION
+            )
+            .into());
+        }
+        let mut value = value;
+        //version 2まではロックが(room, period)の組だった
+        if version < 3 {
+            migrate_locks(&mut value, version);
+        }
+        let mut project = if version == 0 {
+            Project::from_legacy_dump(value, None)?
+        } else {
+            serde_json::from_value::<Project>(value)?
+        };
+        //version 1まではセルのidが座標から計算されていた
+        if version < 2 {
+            project.refresh_cell_ids();
+        }
+        project.version = PROJECT_VERSION;
+        Ok(project)
+    }
+
+    fn refresh_cell_ids(&mut self) {
+        if let Some(timetable) = self.timetable.as_mut() {
+            timetable.refresh_cell_ids(&self.input);
+        }
+        for snapshot in self.snapshots.iter_mut() {
+            snapshot.timetable.refresh_cell_ids(&self.input);
+        }
+    }
+
+    fn from_legacy_dump(
+        solver: Value,
+        timetable: Option<Value>,
+    ) -> Result<Project, Box<dyn Error>> {
+        let solver: ACOSolver = serde_json::from_value(solver)?;
+        let timetable: Option<TimeTable> = match timetable {
+            Some(timetable) => Some(serde_json::from_value(timetable)?),
+            None => None,
+        };
+        info!("migrated legacy dump to project version {}", PROJECT_VERSION);
+        Ok(Project::new(
+            solver.input.clone(),
+            solver.parameters.clone(),
+            Some(solver),
+            timetable,
+        ))
+    }
+
+    //以前のconfig_dirに保存されたtimetable.jsonとsolver.jsonを読み込む
+    pub fn open_legacy_dump(dir: &Path) -> Result<Project, Box<dyn Error>> {
+        let mut solver = read_json(&dir.join(LEGACY_DUMP_SOLVER_FILE))?;
+        migrate_locks(&mut solver, 0);
+        let timetable_path = dir.join(LEGACY_DUMP_TIMETABLE_FILE);
+        let timetable = if timetable_path.exists() {
+            Some(read_json(&timetable_path)?)
+        } else {
+            None
+        };
+        let mut project = Project::from_legacy_dump(solver, timetable)?;
+        project.refresh_cell_ids();
+        project.apply_locks();
+        Ok(project)
+    }
+
+    fn apply_locks(&mut self) {
+        if let Some(solver) = self.solver.as_mut() {
+            solver.input = self.input.clone();
+            let graph = solver.colony.get_graph_as_mut();
+            for (class_index, lock) in self.locks.iter().enumerate() {
+                if class_index < graph.get_all_classes_is_locked().len() {
+                    graph.set_classes_is_locked(class_index, *lock);
+                }
+            }
+        }
+    }
+}
+
+//[room, period] -> {"kind": "both", "room": room, "period": period}
+fn migrate_lock_list(locks: Option<&mut Value>) {
+    if let Some(Value::Array(locks)) = locks {
+        for lock in locks.iter_mut() {
+            if let Some([room, period]) = lock.as_array().map(|x| x.as_slice()) {
+                *lock = serde_json::json!({
+                    "kind": "both",
+                    "room": room,
+                    "period": period,
+                });
+            }
+        }
+    }
+}
+
+fn migrate_locks(value: &mut Value, version: u64) {
+    //version 0はsolverそのもの
+    let solver = if version == 0 {
+        Some(&mut *value)
+    } else {
+        value.get_mut("solver")
+    };
+    if let Some(graph) = solver
+        .and_then(|x| x.get_mut("colony"))
+        .and_then(|x| x.get_mut("graph"))
+    {
+        migrate_lock_list(graph.get_mut("classes_is_locked"));
+    }
+    if version > 0 {
+        migrate_lock_list(value.get_mut("locks"));
+    }
+}
+
+fn read_json(path: &Path) -> Result<Value, Box<dyn Error>> {
+    let mut file = File::open(path)?;
+    let mut json = String::new();
+    file.read_to_string(&mut json)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+pub fn get_default_dump_dir() -> Option<PathBuf> {
+    let mut path = config_dir()?;
+    path.push(DUMP_PATH);
+    Some(path)
+}
+
+pub fn get_default_project_path() -> Option<PathBuf> {
+    Some(get_default_dump_dir()?.join(DUMP_PROJECT_FILE))
+}
+
+fn get_config_dir_error() -> CommandError {
+    CommandError::Io("no config directory".to_string())
+}
+
+pub fn save_project(path: &Path, session: &Session) -> Result<(), CommandError> {
+    let input = session.get_input()?.clone();
+    let parameters = session.get_parameters()?.clone();
+    let (solver, timetable) = match session.get_timetable_and_solver() {
+        Ok((timetable, solver)) => (Some(solver.clone()), Some(timetable.clone())),
+        Err(_) => (session.get_solver().ok().cloned(), None),
+    };
+    let mut project = Project::new(input, parameters, solver, timetable);
+    project.snapshots = session.snapshots.get_snapshots().clone();
+    project.save(path).map_err(CommandError::from)
+}
+
+pub fn adapt_project(
+    project: Project,
+    session: &mut Session,
+) -> Result<Option<TimeTable>, CommandError> {
+    session.restore(
+        Some(project.input),
+        Some(project.parameters),
+        project.solver,
+        project.timetable,
+    )?;
+    session.snapshots.set_snapshots(project.snapshots);
+    Ok(session.clone_timetable_if_present())
+}
+
+#[tauri::command]
+pub fn handle_save_project(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<(), CommandError> {
+    info!("called handle_save_project {}", path);
+    let session = session_manager.session.lock_state()?;
+    save_project(Path::new(&path), &session)
+}
+
+#[tauri::command]
+pub fn handle_open_project(
+    session_manager: tauri::State<'_, SessionManager>,
+    path: String,
+) -> Result<Option<TimeTable>, CommandError> {
+    info!("called handle_open_project {}", path);
+    let project = Project::open(Path::new(&path))?;
+    let mut session = session_manager.session.lock_state()?;
+    adapt_project(project, &mut session)
+}
+
+#[tauri::command]
+pub fn dump_timetable(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<(), CommandError> {
+    info!("called dump_timetable");
+    let path = get_default_project_path().ok_or_else(get_config_dir_error)?;
+    let session = session_manager.session.lock_state()?;
+    save_project(&path, &session)
+}
+
+#[tauri::command]
+pub fn load_timetable(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<TimeTable, CommandError> {
+    info!("called load_timetable");
+    let path = get_default_project_path().ok_or_else(get_config_dir_error)?;
+    //project.jsonが無ければ以前のtimetable.jsonとsolver.jsonから移行する
+    let project = if path.exists() {
+        Project::open(&path)
+    } else {
+        Project::open_legacy_dump(&get_default_dump_dir().ok_or_else(get_config_dir_error)?)
+    }?;
+    let mut session = session_manager.session.lock_state()?;
+    adapt_project(project, &mut session)?.ok_or(CommandError::NoTimetable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::aco::graph::LockKind;
+    use crate::input::input_file::InputFile;
+    use serde_json::json;
+
+    //授業2つ、class idは100と101、授業0を教室1の3コマ目に固定した今の形式のプロジェクト
+    fn create_project() -> Project {
+        let json = r#"{
+            "version": 1,
+            "teachers": [],
+            "studentGroups": [],
+            "rooms": [{"id": 10, "name": "R1", "capacity": 40}, {"id": 11, "name": "R2", "capacity": 40}],
+            "classes": [
+                {"id": 100, "name": "C1", "rooms": [10, 11], "numOfStudents": 0},
+                {"id": 101, "name": "C2", "rooms": [10, 11], "numOfStudents": 0}
+            ]
+        }"#;
+        let file: InputFile = serde_json::from_str(json).unwrap();
+        let input = file.into_input().unwrap();
+        let parameters = crate::sized_aco_parameters(&input, 10, 5);
+        let mut solver = crate::reset_aco_solver(&input, &parameters);
+        let mut timetable = TimeTable::new(2, 10, 2);
+        timetable.add_class(1, 3, input.get_classes()[0].clone(), None, &solver);
+        timetable.add_class(0, 7, input.get_classes()[1].clone(), None, &solver);
+        timetable.class_list[0].as_mut().unwrap().is_locked = Some(true);
+        let lock = ClassLock {
+            kind: LockKind::Both,
+            room: 1,
+            period: 3,
+        };
+        solver.colony.get_graph_as_mut().set_classes_is_locked(0, Some(lock));
+        Project::new(input, parameters, Some(solver), Some(timetable))
+    }
+
+    //ロックを[room, period]に戻し、セルのidを座標から計算していた頃の値にする
+    fn to_legacy_value(project: &Project, version: u64) -> Value {
+        let mut value = serde_json::to_value(project).unwrap();
+        value["version"] = json!(version);
+        value["locks"] = json!([[1, 3], null]);
+        value["solver"]["colony"]["graph"]["classes_is_locked"] = json!([[1, 3], null]);
+        if version < 2 {
+            for (i, cell) in value["timetable"]["classList"]
+                .as_array_mut()
+                .unwrap()
+                .iter_mut()
+                .enumerate()
+            {
+                cell["id"] = json!(1000 + i);
+            }
+        }
+        value
+    }
+
+    fn assert_migrated(project: &Project) {
+        let expected = json!({"kind": "both", "room": 1, "period": 3});
+        assert_eq!(project.version, PROJECT_VERSION);
+        assert_eq!(serde_json::to_value(project.locks[0]).unwrap(), expected);
+        assert_eq!(project.locks[1], None);
+        let graph = project.solver.as_ref().unwrap().colony.get_graph();
+        assert_eq!(serde_json::to_value(graph.get_classes_is_locked(0)).unwrap(), expected);
+        assert_eq!(graph.get_classes_is_locked(1), None);
+        if let Some(timetable) = project.timetable.as_ref() {
+            let ids = timetable
+                .class_list
+                .iter()
+                .map(|x| x.as_ref().unwrap().id)
+                .collect::<Vec<usize>>();
+            assert_eq!(ids, vec![100, 101]);
+        }
+    }
+
+    fn migrate_and_apply(value: Value) -> Project {
+        let mut project = Project::migrate(value).unwrap();
+        project.apply_locks();
+        project
+    }
+
+    #[test]
+    fn migrate_v2_to_v3() {
+        let project = migrate_and_apply(to_legacy_value(&create_project(), 2));
+        assert_migrated(&project);
+    }
+
+    #[test]
+    fn migrate_v1_to_v3() {
+        let project = migrate_and_apply(to_legacy_value(&create_project(), 1));
+        assert_migrated(&project);
+    }
+
+    //version 0はversionのないsolver.jsonと、別のtimetable.json
+    #[test]
+    fn migrate_v0_to_v3() {
+        let value = to_legacy_value(&create_project(), 1);
+        let project = migrate_and_apply(value["solver"].clone());
+        assert_migrated(&project);
+
+        let dir = std::env::temp_dir().join(format!("fun-timetabling-legacy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(LEGACY_DUMP_SOLVER_FILE), value["solver"].to_string()).unwrap();
+        std::fs::write(dir.join(LEGACY_DUMP_TIMETABLE_FILE), value["timetable"].to_string()).unwrap();
+        let project = Project::open_legacy_dump(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let project = project.unwrap();
+        assert!(project.timetable.is_some());
+        assert_migrated(&project);
+    }
+}
@@ -0,0 +1,301 @@
+// 入力・ACOのパラメータ・solver・時間割を1つのMutexでまとめて持つ
+// 入力を読み込む → solverを作る → 時間割ができる、の順に進み、
+// 前の段階を作り直すと後ろの段階は捨てる
+use crate::algorithm::aco::aco_parameters::AcoParameters;
+use crate::algorithm::aco::aco_solver::ACOSolver;
+use crate::algorithm::time_table::explanation::Language;
+use crate::algorithm::time_table::history::{EditHistory, EditKind};
+use crate::algorithm::time_table::snapshot::SnapshotStore;
+use crate::algorithm::time_table::weak_placement::WeakPlacementConfig;
+use crate::algorithm::time_table::TimeTable;
+use crate::error::{CommandError, LockState};
+use crate::input::Input;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionStage {
+    Empty,
+    InputLoaded,
+    SolverReady,
+    TimetablePresent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionState {
+    pub stage: SessionStage,
+    pub revision: u64,
+    //入力を読み直した後も、再計画のために前のsolverと時間割は残っている
+    pub has_previous_solution: bool,
+}
+
+//入力を読み直す前の時間割、prepare_solverでは消えない
+#[derive(Clone)]
+pub struct PreviousSolution {
+    pub input: Input,
+    pub parameters: AcoParameters,
+    pub timetable: TimeTable,
+}
+
+#[derive(Default)]
+pub struct Session {
+    //状態が変わるたびに増える、UIはこれを編集コマンドに渡す
+    revision: u64,
+    input: Option<Input>,
+    //何回目に読み込んだ入力か、solverがどの入力から作られたかの判定に使う
+    input_generation: u64,
+    parameters: Option<AcoParameters>,
+    solver: Option<ACOSolver>,
+    solver_input_generation: u64,
+    timetable: Option<TimeTable>,
+    previous_solution: Option<PreviousSolution>,
+    pub history: EditHistory,
+    pub weak_placement: WeakPlacementConfig,
+    pub language: Language,
+    pub snapshots: SnapshotStore,
+}
+
+impl Session {
+    pub fn get_stage(&self) -> SessionStage {
+        if self.input.is_none() {
+            return SessionStage::Empty;
+        }
+        if self.solver.is_none() || self.solver_input_generation != self.input_generation {
+            return SessionStage::InputLoaded;
+        }
+        if self.timetable.is_none() {
+            return SessionStage::SolverReady;
+        }
+        SessionStage::TimetablePresent
+    }
+
+    pub fn get_state(&self) -> SessionState {
+        SessionState {
+            stage: self.get_stage(),
+            revision: self.revision,
+            has_previous_solution: self.get_previous_solution().is_some(),
+        }
+    }
+
+    //UIが見ている時間割より後に状態が変わっていたら操作を弾く、省略したときは確認しない
+    pub fn check_revision(&self, revision: Option<u64>) -> Result<(), CommandError> {
+        match revision {
+            Some(revision) if revision != self.revision => Err(CommandError::StaleRevision {
+                expected: revision,
+                current: self.revision,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    //solverのフェロモンや設定だけを変えたとき
+    pub fn touch(&mut self) {
+        self.revision += 1;
+    }
+
+    //入力を読み直すと、それまでの時間割は再計画の元としてだけ残る
+    pub fn load_input(&mut self, input: Input) {
+        info!("session: input loaded");
+        if let Ok((timetable, solver)) = self.get_timetable_and_solver() {
+            self.previous_solution = Some(PreviousSolution {
+                input: solver.input.clone(),
+                parameters: solver.get_parameters(),
+                timetable: timetable.clone(),
+            });
+        }
+        self.input = Some(input);
+        self.input_generation += 1;
+        self.parameters = None;
+        self.history.clear();
+        self.revision += 1;
+    }
+
+    //solverを作り直すと、前のsolverから作った時間割は捨てる
+    pub fn prepare_solver(
+        &mut self,
+        parameters: AcoParameters,
+        solver: ACOSolver,
+    ) -> Result<(), CommandError> {
+        if self.input.is_none() {
+            return Err(CommandError::NoInput);
+        }
+        info!("session: solver ready");
+        self.parameters = Some(parameters);
+        self.solver = Some(solver);
+        self.solver_input_generation = self.input_generation;
+        self.timetable = None;
+        self.history.clear();
+        self.revision += 1;
+        Ok(())
+    }
+
+    //solverから作り直した時間割は手動編集の履歴を引き継がない
+    pub fn set_timetable(&mut self, timetable: TimeTable) -> Result<(), CommandError> {
+        if self.get_stage() < SessionStage::SolverReady {
+            return Err(CommandError::NoSolver);
+        }
+        self.timetable = Some(timetable);
+        self.history.clear();
+        self.revision += 1;
+        Ok(())
+    }
+
+    //プロジェクトを開いたときにまとめて置き換える
+    pub fn restore(
+        &mut self,
+        input: Option<Input>,
+        parameters: Option<AcoParameters>,
+        solver: Option<ACOSolver>,
+        timetable: Option<TimeTable>,
+    ) -> Result<(), CommandError> {
+        if solver.is_some() && input.is_none() {
+            return Err(CommandError::NoInput);
+        }
+        if timetable.is_some() && solver.is_none() {
+            return Err(CommandError::NoSolver);
+        }
+        self.input = input;
+        self.input_generation += 1;
+        self.parameters = parameters;
+        self.solver = solver;
+        self.solver_input_generation = self.input_generation;
+        self.timetable = timetable;
+        self.previous_solution = None;
+        self.history.clear();
+        self.revision += 1;
+        Ok(())
+    }
+
+    pub fn get_input(&self) -> Result<&Input, CommandError> {
+        self.input.as_ref().ok_or(CommandError::NoInput)
+    }
+
+    pub fn get_parameters(&self) -> Result<&AcoParameters, CommandError> {
+        self.parameters.as_ref().ok_or(CommandError::NoParameters)
+    }
+
+    pub fn get_parameters_mut(&mut self) -> Result<&mut AcoParameters, CommandError> {
+        self.parameters.as_mut().ok_or(CommandError::NoParameters)
+    }
+
+    fn check_solver(&self) -> Result<(), CommandError> {
+        match self.get_stage() {
+            SessionStage::Empty => Err(CommandError::NoInput),
+            SessionStage::InputLoaded if self.solver.is_some() => Err(CommandError::OutdatedSolver),
+            SessionStage::InputLoaded => Err(CommandError::NoSolver),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_solver(&self) -> Result<&ACOSolver, CommandError> {
+        self.check_solver()?;
+        self.solver.as_ref().ok_or(CommandError::NoSolver)
+    }
+
+    pub fn get_solver_mut(&mut self) -> Result<&mut ACOSolver, CommandError> {
+        self.check_solver()?;
+        self.solver.as_mut().ok_or(CommandError::NoSolver)
+    }
+
+    pub fn get_timetable(&self) -> Result<&TimeTable, CommandError> {
+        self.check_solver()?;
+        self.timetable.as_ref().ok_or(CommandError::NoTimetable)
+    }
+
+    //時間割とsolverを同時に借りる
+    pub fn get_timetable_and_solver(&self) -> Result<(&TimeTable, &ACOSolver), CommandError> {
+        let timetable = self.get_timetable()?;
+        Ok((timetable, self.get_solver()?))
+    }
+
+    //編集した後はrecord_editを呼ぶ
+    pub fn get_editable(&mut self) -> Result<(&mut TimeTable, &ACOSolver), CommandError> {
+        self.check_solver()?;
+        let timetable = self.timetable.as_mut().ok_or(CommandError::NoTimetable)?;
+        let solver = self.solver.as_ref().ok_or(CommandError::NoSolver)?;
+        Ok((timetable, solver))
+    }
+
+    pub fn record_edit(&mut self, kind: EditKind, before: TimeTable) -> Result<(), CommandError> {
+        let after = self.get_timetable()?.clone();
+        self.history.record(kind, before, after);
+        self.revision += 1;
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Result<(), CommandError> {
+        self.get_timetable()?;
+        let timetable = self
+            .history
+            .undo()
+            .ok_or(CommandError::InvalidOperation("nothing to undo".to_string()))?;
+        self.timetable = Some(timetable);
+        self.revision += 1;
+        Ok(())
+    }
+
+    pub fn redo(&mut self) -> Result<(), CommandError> {
+        self.get_timetable()?;
+        let timetable = self
+            .history
+            .redo()
+            .ok_or(CommandError::InvalidOperation("nothing to redo".to_string()))?;
+        self.timetable = Some(timetable);
+        self.revision += 1;
+        Ok(())
+    }
+
+    //入力を読み直す前の時間割、再計画の元にする
+    pub fn get_previous_solution(&self) -> Option<&PreviousSolution> {
+        self.previous_solution.as_ref()
+    }
+
+    //UIに返す前に弱い配置の印と違反の説明を付け直す
+    pub fn annotate(&mut self) -> Result<(), CommandError> {
+        let language = self.language;
+        let config = self.weak_placement.clone();
+        let (timetable, solver) = self.get_editable()?;
+        timetable.update_weak_cells(solver, &config);
+        timetable.update_tool_tip_messages(
+            &solver.input,
+            solver.parameters.num_of_day_lengths,
+            language,
+        );
+        Ok(())
+    }
+
+    //UIに返す時間割には今のrevisionを付ける
+    pub fn clone_timetable(&self) -> Result<TimeTable, CommandError> {
+        let mut timetable = self.get_timetable()?.clone();
+        timetable.revision = self.revision;
+        Ok(timetable)
+    }
+
+    //時間割がまだ無いときはNone
+    pub fn clone_timetable_if_present(&self) -> Option<TimeTable> {
+        self.clone_timetable().ok()
+    }
+}
+
+pub struct SessionManager {
+    pub session: Mutex<Session>,
+}
+
+impl SessionManager {
+    pub fn new() -> SessionManager {
+        SessionManager {
+            session: Mutex::new(Session::default()),
+        }
+    }
+}
+
+#[tauri::command]
+pub fn handle_get_session_state(
+    session_manager: tauri::State<'_, SessionManager>,
+) -> Result<SessionState, CommandError> {
+    info!("called handle_get_session_state");
+    Ok(session_manager.session.lock_state()?.get_state())
+}
@@ -15,6 +15,7 @@ use serde::{Deserialize, Serialize};
 use student_group::StudentGroup;
 use teacher::Teacher;
 use log::info;
+use crate::error::CommandError;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum TableType {
@@ -25,22 +26,25 @@ pub enum TableType {
 }
 
 #[tauri::command]
-pub fn handle_get_table(table_type: String) -> Result<TableType, String> {
+pub fn handle_get_table(table_type: String) -> Result<TableType, CommandError> {
     info!("table_type: {}", table_type);
     if table_type == "teachers" {
-        let res = Teachers::new().map_err(|e| e.to_string())?;
+        let res = Teachers::new()?;
         return Ok(TableType::Teachers(res));
     } else if table_type == "studentGroups" {
-        let res = StudentGroups::new().map_err(|e| e.to_string())?;
+        let res = StudentGroups::new()?;
         return Ok(TableType::StudentGroups(res));
     } else if table_type == "classes" {
-        let res = Classes::new().map_err(|e| e.to_string())?;
+        let res = Classes::new()?;
         return Ok(TableType::Classes(res));
     } else if table_type == "rooms" {
-        let res = Rooms::new().map_err(|e| e.to_string())?;
+        let res = Rooms::new()?;
         return Ok(TableType::Rooms(res));
     }
-    return Err("Table type not found".to_string());
+    return Err(CommandError::InvalidArgument(format!(
+        "table type not found: {}",
+        table_type
+    )));
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -60,7 +64,7 @@ impl Classes {
             .from_path(CLASSES_CSV_PATH)?;
         let mut columns = Vec::<Column>::new();
         let mut classes = Vec::<Class>::new();
-        let first_record = rdr.records().next().unwrap()?;
+        let first_record = rdr.records().next().ok_or("no header row")??;
         columns.push(Column {
             header: first_record[0].to_string(),
             accessor: "id".to_string(),
@@ -131,7 +135,7 @@ impl Rooms {
             .from_path(ROOMS_CSV_PATH)?;
         let mut columns = Vec::<column::Column>::new();
         let mut rooms = Vec::new();
-        let first_record = rdr.records().next().unwrap()?;
+        let first_record = rdr.records().next().ok_or("no header row")??;
         columns.push(column::Column {
             header: first_record[0].to_string(),
             accessor: "id".to_string(),
@@ -175,7 +179,7 @@ impl StudentGroups {
             .from_path(STUDENT_GROUPS_CSV_PATH)?;
         let mut columns = Vec::<column::Column>::new();
         let mut student_groups = Vec::new();
-        let first_record = rdr.records().next().unwrap()?;
+        let first_record = rdr.records().next().ok_or("no header row")??;
         columns.push(column::Column {
             header: first_record[0].to_string(),
             accessor: "id".to_string(),
@@ -213,7 +217,7 @@ impl Teachers {
             .from_path(TEACHERS_CSV_PATH)?;
         let mut columns = Vec::<column::Column>::new();
         let mut teachers = Vec::new();
-        let first_record = rdr.records().next().unwrap()?;
+        let first_record = rdr.records().next().ok_or("no header row")??;
         columns.push(column::Column {
             header: first_record[0].to_string(),
             accessor: "id".to_string(),
@@ -3,6 +3,13 @@ import { invoke } from "@tauri-apps/api/tauri";
 import Grid from "./Grid/Grid";
 import { useEffect, useState } from "react";
 import { TimeTable } from "./Grid/Grid";
+import { getErrorMessage } from "../modules/commandError";
+import {
+  AcoRunResult,
+  StoppingCriteria,
+  getRunSummaryMessage,
+  parseOptionalNumber,
+} from "../modules/stoppingCriteria";
 
 interface GeneratorProps {
   tableNames: string[];
@@ -17,6 +24,11 @@ const Generator: React.FC<GeneratorProps> = ({ tableNames }) => {
   let [rooms, SetRooms] = useState([] as string[]);
   let [periods, SetPeriods] = useState([] as string[]);
   let [showColor, setShowColor] = useState(true);
+  let [timeLimitSecs, setTimeLimitSecs] = useState("10");
+  let [maxGenerations, setMaxGenerations] = useState("");
+  let [noImprovementGenerations, setNoImprovementGenerations] = useState("");
+  let [zeroHardViolations, setZeroHardViolations] = useState(true);
+  let [runMessage, setRunMessage] = useState("");
 
   useEffect(() => {
     invoke<string[]>("handle_get_rooms")
@@ -24,7 +36,7 @@ const Generator: React.FC<GeneratorProps> = ({ tableNames }) => {
         SetRooms(res);
       })
       .catch((err) => {
-        SetRooms([err]);
+        SetRooms([getErrorMessage(err)]);
       });
 
     invoke<string[]>("handle_get_periods")
@@ -32,7 +44,7 @@ const Generator: React.FC<GeneratorProps> = ({ tableNames }) => {
         SetPeriods(res);
       })
       .catch((err) => {
-        SetPeriods([err]);
+        SetPeriods([getErrorMessage(err)]);
       });
   }, [timeTable]);
 
@@ -47,7 +59,10 @@ const Generator: React.FC<GeneratorProps> = ({ tableNames }) => {
   };
   const run_once = () => {
     if (timeTable.classList.length != 0) {
-      invoke("handle_read_cells", { cells: timeTable.classList });
+      invoke("handle_read_cells", {
+        cells: timeTable.classList,
+        revision: timeTable.revision,
+      });
     }
     invoke<TimeTable>("handle_aco_run_once")
       .then((res) => {
@@ -59,7 +74,10 @@ const Generator: React.FC<GeneratorProps> = ({ tableNames }) => {
   };
   const run_no_violation = () => {
     if (timeTable.classList.length != 0) {
-      invoke("handle_read_cells", { cells: timeTable.classList });
+      invoke("handle_read_cells", {
+        cells: timeTable.classList,
+        revision: timeTable.revision,
+      });
     }
     invoke<TimeTable>("handle_aco_run_no_violations")
       .then((res) => {
@@ -70,6 +88,30 @@ const Generator: React.FC<GeneratorProps> = ({ tableNames }) => {
       });
   };
 
+  const run_with_criteria = () => {
+    const criteria: StoppingCriteria = {
+      timeLimitSecs: parseOptionalNumber(timeLimitSecs),
+      maxGenerations: parseOptionalNumber(maxGenerations),
+      noImprovementGenerations: parseOptionalNumber(noImprovementGenerations),
+      zeroHardViolations: zeroHardViolations,
+    };
+    if (timeTable.classList.length != 0) {
+      invoke("handle_read_cells", {
+        cells: timeTable.classList,
+        revision: timeTable.revision,
+      });
+    }
+    setRunMessage("計算中...");
+    invoke<AcoRunResult>("handle_aco_run_with_criteria", { criteria: criteria })
+      .then((res) => {
+        setTimeTable(res.timetable);
+        setRunMessage(getRunSummaryMessage(res.summary));
+      })
+      .catch((err) => {
+        setRunMessage(getErrorMessage(err));
+      });
+  };
+
   const save_time_table = () => {
     invoke("dump_timetable");
   };
@@ -97,7 +139,9 @@ const Generator: React.FC<GeneratorProps> = ({ tableNames }) => {
   };
   
   const handle_lock_no_violation = () => {
-    invoke<TimeTable>("handle_lock_no_violation")
+    invoke<TimeTable>("handle_lock_no_violation", {
+      revision: timeTable.revision,
+    })
       .then((res) => {
         setTimeTable(res);
       })
@@ -107,7 +151,9 @@ const Generator: React.FC<GeneratorProps> = ({ tableNames }) => {
   }
   
   const handle_unlock_violation = () => {
-    invoke<TimeTable>("handle_unlock_violation")
+    invoke<TimeTable>("handle_unlock_violation", {
+      revision: timeTable.revision,
+    })
       .then((res) => {
         setTimeTable(res);
       })
@@ -122,6 +168,42 @@ const Generator: React.FC<GeneratorProps> = ({ tableNames }) => {
       <button onClick={sendClassData}>はじめから時間割を作る</button>
       <button onClick={run_once}>さらに時間割を計算する</button>
       <button onClick={run_no_violation}>制約違反がなくなるまで計算する。</button>
+      <div>
+        <label>
+          制限時間(秒)
+          <input
+            type="number"
+            value={timeLimitSecs}
+            onChange={(e) => setTimeLimitSecs(e.target.value)}
+          ></input>
+        </label>
+        <label>
+          最大世代数
+          <input
+            type="number"
+            value={maxGenerations}
+            onChange={(e) => setMaxGenerations(e.target.value)}
+          ></input>
+        </label>
+        <label>
+          改善なしで止める世代数
+          <input
+            type="number"
+            value={noImprovementGenerations}
+            onChange={(e) => setNoImprovementGenerations(e.target.value)}
+          ></input>
+        </label>
+        <label>
+          <input
+            type="checkbox"
+            checked={zeroHardViolations}
+            onChange={() => setZeroHardViolations(!zeroHardViolations)}
+          ></input>
+          制約違反がなくなったら止める
+        </label>
+        <button onClick={run_with_criteria}>条件を指定して計算する</button>
+        <span>{runMessage}</span>
+      </div>
       <button onClick={handle_lock_no_violation}>制約違反以外のコマをロック</button>
       <button onClick={handle_unlock_violation}>制約違反のコマをアンロック</button>
       <button onClick={save_time_table}>時間割を保存</button>
@@ -1,6 +1,6 @@
 import { useDraggable } from "@dnd-kit/core";
 import { invoke } from "@tauri-apps/api/tauri";
-import { TimeTable } from "../Grid";
+import { LockKind, TimeTable } from "../Grid";
 import { Tooltip } from "react-tooltip";
 import React, { useCallback } from "react";
 
@@ -17,11 +17,27 @@ interface DraggableProps {
   ) => void;
   isViolated: boolean;
   toolTipMessage: string;
-  isWorst3: boolean;
+  isWeak: boolean;
   showColor: boolean;
   isLocked: boolean;
+  lockKind: LockKind | null;
+  revision?: number;
 }
 
+//右クリックで 固定なし → 両方 → コマだけ → 部屋だけ の順に切り替える
+const nextLockKind = (lockKind: LockKind | null): LockKind | null => {
+  switch (lockKind) {
+    case null:
+      return "both";
+    case "both":
+      return "periodOnly";
+    case "periodOnly":
+      return "roomOnly";
+    case "roomOnly":
+      return null;
+  }
+};
+
 export function Draggable({
   hex_color,
   text,
@@ -33,9 +49,11 @@ export function Draggable({
   setTimeTable,
   isViolated,
   toolTipMessage,
-  isWorst3,
+  isWeak,
   showColor,
   isLocked,
+  lockKind,
+  revision,
 }: DraggableProps) {
   const { attributes, listeners, setNodeRef, transform } = useDraggable({
     id: id.toString(),
@@ -53,7 +71,7 @@ const style = {
       ? "#FFFFFF" // 背景色を白に設定
       : hex_color, // 色情報を表示
   backgroundImage:
-    showColor && isWorst3 // showColor が true の場合のみストライプ模様を適用
+    showColor && isWeak // showColor が true の場合のみストライプ模様を適用
       ? `repeating-linear-gradient(
           45deg,
           #f0ad4e 0,
@@ -69,13 +87,30 @@ const style = {
 };
 
   const handleDoubleClick = useCallback(() => {
-    invoke<TimeTable>("handle_switch_lock", { id })
+    invoke<TimeTable>("handle_switch_lock", { classId: id, revision })
       .then(setTimeTable)
       .catch((err) => {
         console.error(err);
         alert("ロック/アンロックの操作中にエラーが発生しました");
       });
-  }, [id, setTimeTable]);
+  }, [id, revision, setTimeTable]);
+
+  const handleContextMenu = useCallback(
+    (event: React.MouseEvent) => {
+      event.preventDefault();
+      invoke<TimeTable>("handle_set_lock", {
+        classId: id,
+        kind: nextLockKind(lockKind),
+        revision,
+      })
+        .then(setTimeTable)
+        .catch((err) => {
+          console.error(err);
+          alert("ロックの切り替え中にエラーが発生しました");
+        });
+    },
+    [id, lockKind, revision, setTimeTable],
+  );
 
   return (
     <>
@@ -84,6 +119,7 @@ const style = {
         {...listeners}
         {...attributes}
         onClick={handleDoubleClick}
+        onContextMenu={handleContextMenu}
         style={style}
         className={styles}
         data-tooltip-id={id.toString()}
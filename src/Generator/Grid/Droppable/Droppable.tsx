@@ -12,6 +12,7 @@ interface droppableProps {
 export function Droppable({ id,styles,room,period,grid_size ,overColor}: droppableProps) {
   const { isOver, setNodeRef } = useDroppable({
     id: id.toString(),
+    data: { room, period },
   });
   room = room + 2;
   period = period + 2;
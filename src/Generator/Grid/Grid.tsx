@@ -48,12 +48,15 @@ class ActiveCell {
   studentSize?: number;
   color?: string;
   isLocked?: boolean;
+  lockKind?: LockKind;
   size?: number;
   violations?: cellsViolations;
   toolTipMessage: string;
-  isWorst3?: boolean;
+  isWeak?: boolean;
 }
 
+export type LockKind = "both" | "periodOnly" | "roomOnly";
+
 class BlankCell {
   id: number;
   period: number;
@@ -72,6 +75,8 @@ export interface TimeTable {
   classList: (ActiveCell | null)[];
   roomSize: number;
   periodSize: number;
+  //表示している時間割のrevision、編集コマンドに渡す
+  revision?: number;
 }
 
 interface GridProps {
@@ -106,8 +111,8 @@ const Grid: React.FC<GridProps> = ({
     }
     let is_swappable;
     invoke<boolean>("is_swappable", {
-      overId: Number(over.id),
-      activeId: Number(active.id),
+      classId: Number(active.id),
+      to: over.data.current,
     })
       .then((res) => {
         is_swappable = res;
@@ -115,8 +120,9 @@ const Grid: React.FC<GridProps> = ({
           return;
         }
         invoke<TimeTable>("handle_swap_cell", {
-          overId: Number(over.id),
-          activeId: Number(active.id),
+          classId: Number(active.id),
+          to: over.data.current,
+          revision: timeTable.revision,
         })
           .then((res) => {
             setTimeTable(res);
@@ -138,8 +144,8 @@ const Grid: React.FC<GridProps> = ({
       return;
     }
     invoke<boolean>("is_swappable", {
-      overId: Number(over.id),
-      activeId: Number(active.id),
+      classId: Number(active.id),
+      to: over.data.current,
     })
       .then((res) => {
         if (res) {
@@ -163,66 +169,8 @@ const Grid: React.FC<GridProps> = ({
         >
           {classList.map((cell, index) => {
             if (cell != null) {
-              let tipMessage = "";
-              if (cell.violations) {
-                if (cell.violations.isViolated) {
-                  console.log(cell.violations);
-                  if (cell.violations.sameStudentSameTime.length > 0) {
-                    tipMessage +=
-                      "\n同じクラスの人が同じ時間に別の部屋にいます。";
-                    tipMessage += cell.violations.sameStudentSameTime.map(
-                      (violation) => {
-                        return (
-                          "\n時間: " +
-                          violation.period +
-                          " 部屋: " +
-                          violation.rooms.join(",")
-                        );
-                      },
-                    );
-                  }
-                  if (cell.violations.sameTeacherSameTime.length > 0) {
-                    tipMessage +=
-                      "\n同じ先生が同じ時間に別の部屋にいます。";
-                    tipMessage += cell.violations.sameTeacherSameTime.map(
-                      (violation) => {
-                        return (
-                          "\n時間: " +
-                          violation.period +
-                          " 部屋: " +
-                          violation.rooms.join(",")
-                        );
-                      },
-                    );
-                  }
-                  if (cell.violations.capacityOver.length > 0) {
-                    tipMessage += "\n容量オーバーです。";
-                    tipMessage += cell.violations.capacityOver.map(
-                      (violation) => {
-                        return (
-                          "\n時間: " +
-                          violation.period +
-                          " 部屋: " +
-                          violation.rooms.join(",")
-                        );
-                      },
-                    );
-                  }
-                  if (cell.violations.strabbleDays.length > 0) {
-                    tipMessage += "\n授業が日をまたいでいます。";
-                    tipMessage += cell.violations.strabbleDays.map(
-                      (violation) => {
-                        return (
-                          "\n時間: " +
-                          violation.period +
-                          " 部屋: " +
-                          violation.rooms.join(",")
-                        );
-                      },
-                    );
-                  }
-                }
-              }
+              //違反の説明はバックエンドで作る
+              const tipMessage = cell.toolTipMessage;
               return (
                 <Draggable
                   hex_color={cell.color ? cell.color : "#FFFFFF"}
@@ -238,9 +186,11 @@ const Grid: React.FC<GridProps> = ({
                   setTimeTable={setTimeTable}
                   isViolated={cell.violations?.isViolated!}
                   toolTipMessage={tipMessage}
-                  isWorst3={cell.isWorst3? cell.isWorst3: false}
+                  isWeak={cell.isWeak ? cell.isWeak : false}
                   showColor={showColor}
                   isLocked={cell.isLocked? cell.isLocked: false}
+                  lockKind={cell.isLocked ? cell.lockKind ?? "both" : null}
+                  revision={timeTable.revision}
                 />
               );
             }
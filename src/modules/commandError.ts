@@ -0,0 +1,16 @@
+// バックエンドのコマンドが返すエラー
+export interface CommandError {
+  code: string;
+  message: string;
+}
+
+// 以前のように文字列で返ってきた場合もそのまま表示する
+export function getErrorMessage(err: unknown): string {
+  if (typeof err === "string") {
+    return err;
+  }
+  if (err && typeof err === "object" && "message" in err) {
+    return String((err as CommandError).message);
+  }
+  return String(err);
+}
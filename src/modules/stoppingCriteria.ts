@@ -0,0 +1,51 @@
+import { TimeTable } from "../Generator/Grid/Grid";
+
+// いずれかの条件を満たした時点で停止する
+export interface StoppingCriteria {
+  timeLimitSecs?: number;
+  maxGenerations?: number;
+  targetScore?: number;
+  zeroHardViolations: boolean;
+  noImprovementGenerations?: number;
+}
+
+export type StopReason =
+  | "timeLimit"
+  | "generationLimit"
+  | "targetScore"
+  | "zeroHardViolations"
+  | "noImprovement";
+
+export interface RunSummary {
+  reason: StopReason;
+  generations: number;
+  elapsedSecs: number;
+  score: number;
+  hardViolations: number;
+}
+
+export interface AcoRunResult {
+  timetable: TimeTable;
+  summary: RunSummary;
+}
+
+const stopReasonLabels: { [key in StopReason]: string } = {
+  timeLimit: "制限時間に達した",
+  generationLimit: "最大世代数に達した",
+  targetScore: "目標スコアに達した",
+  zeroHardViolations: "制約違反がなくなった",
+  noImprovement: "改善が止まった",
+};
+
+export function getRunSummaryMessage(summary: RunSummary): string {
+  return `${stopReasonLabels[summary.reason]} (${summary.generations}世代, ${summary.elapsedSecs.toFixed(1)}秒, 違反${summary.hardViolations}件)`;
+}
+
+// 空欄はundefinedにして条件に含めない
+export function parseOptionalNumber(value: string): number | undefined {
+  if (value.trim() === "") {
+    return undefined;
+  }
+  const res = Number(value);
+  return Number.isFinite(res) ? res : undefined;
+}